@@ -1,7 +1,7 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use hearton_public::simd::soa::SimdAabbX4;
-use bevy::render::primitives::Aabb;
-use bevy::math::Vec3;
+use bevy::render::primitives::{Aabb, Frustum};
+use bevy::math::{Mat4, Vec3};
 
 fn benchmark_aabb_intersection(c: &mut Criterion) {
     let aabbs = [
@@ -19,6 +19,16 @@ fn benchmark_aabb_intersection(c: &mut Criterion) {
             black_box(soa_aabb.intersects_aabb(&test_aabb));
         })
     });
+
+    let view_projection = Mat4::perspective_rh(1.0, 16.0 / 9.0, 0.1, 1000.0)
+        * Mat4::look_at_rh(Vec3::new(3.5, 0.5, 10.0), Vec3::new(3.5, 0.5, 0.0), Vec3::Y);
+    let frustum = Frustum::from_view_projection(&view_projection);
+
+    c.bench_function("simd_aabb_frustum", |b| {
+        b.iter(|| {
+            black_box(soa_aabb.intersects_frustum(&frustum));
+        })
+    });
 }
 
 criterion_group!(benches, benchmark_aabb_intersection);
@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: MIT
+//! Convert `.hvox` and `.vox` scenes into `.glb` or `.obj` meshes
+//!
+//! Usage: `hearton_export <input.hvox|input.vox> <output.glb|output.obj>`
+//!
+//! OBJ output writes the MTL file next to it with the same stem.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use hearton_public::voxel::loader::parse_hvox;
+use hearton_public::voxel::vox::parse_vox;
+use hearton_public::voxel::VoxelScene;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [input, output] = args.as_slice() else {
+        eprintln!("usage: hearton_export <input.hvox|input.vox> <output.glb|output.obj>");
+        return ExitCode::from(2);
+    };
+
+    match export(Path::new(input), Path::new(output)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("hearton_export: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn export(input: &Path, output: &Path) -> Result<(), String> {
+    let scene = load(input)?;
+    println!("{}: {} voxels", input.display(), scene.voxel_count());
+
+    match extension(output).as_str() {
+        "glb" => write(output, &scene.to_glb()?),
+        "obj" => {
+            let mtl_path = output.with_extension("mtl");
+            let mtl_file = mtl_path.file_name().and_then(|n| n.to_str()).unwrap_or("scene.mtl");
+            let (obj, mtl) = scene.to_obj(mtl_file);
+            write(output, obj.as_bytes())?;
+            write(&mtl_path, mtl.as_bytes())
+        }
+        other => Err(format!("Unsupported output format: .{}", other)),
+    }
+}
+
+fn load(path: &Path) -> Result<VoxelScene, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("scene");
+    let scene = match extension(path).as_str() {
+        "hvox" => parse_hvox(&bytes),
+        "vox" => parse_vox(&bytes).and_then(|vox| vox.scene(name)),
+        other => return Err(format!("Unsupported input format: .{}", other)),
+    };
+    scene.map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn extension(path: &Path) -> String {
+    path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase()
+}
+
+fn write(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    std::fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e))?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
@@ -1,12 +1,25 @@
 // SPDX-License-Identifier: MIT
 //! Debug HUD and visualization
+//!
+//! F4 cycles [`VisualizationMode`]. Bounds and Wireframe are drawn with
+//! gizmos; the coloring modes set [`DebugColoring`], which makes the voxel
+//! renderers remesh with debug vertex colors.
 
 use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
 use std::collections::VecDeque;
 use std::fs::File;
 use std::io::Write;
 use crate::metrics::PerformanceMetrics;
 use crate::tier::{current_tier, Tier};
+use crate::voxel::mesh_data::VoxelMeshData;
+use crate::voxel::{Voxel, VoxelScene};
+
+/// Chunk triangle count drawn fully red in Performance mode
+pub const PERFORMANCE_TRIANGLE_BUDGET: usize = 4096;
+
+/// Most lines drawn per frame in Wireframe mode
+pub const MAX_WIREFRAME_LINES: usize = 100_000;
 
 /// Debug visualization mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -18,8 +31,112 @@ pub enum VisualizationMode {
     Wireframe,
     /// Show voxel bounds
     Bounds,
-    /// Show performance overlay
+    /// Color chunks by mesh cost
     Performance,
+    /// Color voxels by material ID
+    MaterialId,
+    /// Color faces by their normal
+    Normals,
+}
+
+impl VisualizationMode {
+    /// Next mode in the F4 cycle
+    pub fn next(self) -> Self {
+        match self {
+            VisualizationMode::Normal => VisualizationMode::Wireframe,
+            VisualizationMode::Wireframe => VisualizationMode::Bounds,
+            VisualizationMode::Bounds => VisualizationMode::Performance,
+            VisualizationMode::Performance => VisualizationMode::MaterialId,
+            VisualizationMode::MaterialId => VisualizationMode::Normals,
+            VisualizationMode::Normals => VisualizationMode::Normal,
+        }
+    }
+}
+
+/// Debug vertex coloring the voxel renderers mesh with
+///
+/// Kept separate from [`DebugState`] so renderers only remesh when the
+/// coloring itself changes. The per-voxel cube renderer supports
+/// `MaterialId` only.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebugColoring {
+    /// Regular voxel colors
+    #[default]
+    None,
+    /// Green to red by chunk triangle count
+    Performance,
+    /// One hue per material ID
+    MaterialId,
+    /// Face normal mapped to RGB
+    Normals,
+}
+
+impl DebugColoring {
+    /// Coloring used by a visualization mode
+    pub fn from_mode(mode: VisualizationMode) -> Self {
+        match mode {
+            VisualizationMode::Performance => DebugColoring::Performance,
+            VisualizationMode::MaterialId => DebugColoring::MaterialId,
+            VisualizationMode::Normals => DebugColoring::Normals,
+            _ => DebugColoring::None,
+        }
+    }
+
+    /// Color a voxel is meshed with (alpha is kept so render passes don't change)
+    pub fn voxel_color(self, voxel: &Voxel) -> [u8; 4] {
+        match self {
+            DebugColoring::MaterialId => {
+                // Golden-angle hue steps keep neighboring IDs apart
+                let hue = (voxel.material_id as f32 * 137.508) % 360.0;
+                let [r, g, b, _] = Color::hsl(hue, 0.7, 0.55).as_rgba_u8();
+                [r, g, b, voxel.color[3]]
+            }
+            _ => voxel.color,
+        }
+    }
+
+    /// Apply [`Self::voxel_color`] before meshing
+    pub fn recolor_voxels(self, voxels: &mut [Voxel]) {
+        if self == DebugColoring::MaterialId {
+            for voxel in voxels {
+                voxel.color = self.voxel_color(voxel);
+            }
+        }
+    }
+
+    /// Replace vertex colors of a finished mesh for Normals and Performance
+    pub fn recolor_mesh(self, mesh: &mut VoxelMeshData) {
+        match self {
+            DebugColoring::Normals => {
+                for (color, normal) in mesh.colors.iter_mut().zip(&mesh.normals) {
+                    let [x, y, z] = normal.map(|n| n * 0.5 + 0.5);
+                    *color = [x, y, z, color[3]];
+                }
+            }
+            DebugColoring::Performance => {
+                let cost = (mesh.triangle_count() as f32 / PERFORMANCE_TRIANGLE_BUDGET as f32).min(1.0);
+                for color in &mut mesh.colors {
+                    *color = [cost, 1.0 - cost, 0.0, color[3]];
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Surface-nets mesh of a scene with this coloring applied
+    pub fn smooth_mesh(self, scene: &VoxelScene) -> VoxelMeshData {
+        let mut mesh = if self == DebugColoring::MaterialId {
+            let mut scene = scene.clone();
+            if let crate::voxel::VoxelData::Community(data) = &mut scene.voxel_data {
+                self.recolor_voxels(std::sync::Arc::make_mut(&mut data.voxels).as_mut_slice());
+            }
+            crate::voxel::surface_nets(&scene)
+        } else {
+            crate::voxel::surface_nets(scene)
+        };
+        self.recolor_mesh(&mut mesh);
+        mesh
+    }
 }
 
 /// Debug state
@@ -55,6 +172,116 @@ pub fn toggle_debug_hud(
     }
 }
 
+/// Cycle the visualization mode with F4 key
+pub fn cycle_visualization_mode(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut debug_state: ResMut<DebugState>,
+    mut coloring: ResMut<DebugColoring>,
+) {
+    if keyboard.just_pressed(KeyCode::F4) {
+        let mode = debug_state.visualization_mode.next();
+        debug_state.visualization_mode = mode;
+        debug_state.set_notification(format!("Visualization: {:?}", mode));
+        coloring.set_if_neq(DebugColoring::from_mode(mode));
+        info!("Visualization mode: {:?}", mode);
+    }
+}
+
+/// Run condition: whether a visualization mode is active
+pub fn visualization_mode_is(mode: VisualizationMode) -> impl Fn(Res<DebugState>) -> bool + Clone {
+    move |debug_state: Res<DebugState>| debug_state.visualization_mode == mode
+}
+
+/// Draw scene bounds (yellow) and chunk bounds (green, red when culled)
+pub fn draw_debug_bounds(
+    mut gizmos: Gizmos,
+    scenes: Res<Assets<VoxelScene>>,
+    roots: Query<(&Handle<VoxelScene>, Option<&crate::voxel::VoxelSize>, &GlobalTransform)>,
+    chunks: Query<(&crate::voxel::VoxelChunk, &GlobalTransform, &Visibility)>,
+) {
+    for (handle, size, root) in &roots {
+        let Some(scene) = scenes.get(handle) else { continue };
+        let (x, y, z) = scene.metadata.dimensions;
+        let extent = Vec3::new(x as f32, y as f32, z as f32);
+        // Voxels are centered on their grid position
+        let bounds = Transform::from_translation(extent * 0.5 - Vec3::splat(0.5)).with_scale(extent);
+        let grid = scene.grid_transform(size);
+        gizmos.cuboid(root.mul_transform(grid).mul_transform(bounds), Color::YELLOW);
+    }
+
+    for (chunk, transform, visibility) in &chunks {
+        let aabb = crate::voxel::culling::chunk_world_aabb(chunk.coord, transform);
+        let bounds = Transform::from_translation(aabb.center.into()).with_scale(Vec3::from(aabb.half_extents) * 2.0);
+        let color = if *visibility == Visibility::Hidden { Color::RED } else { Color::GREEN };
+        gizmos.cuboid(bounds, color);
+    }
+}
+
+/// Draw the voxel edges of visible voxel meshes
+///
+/// Meshes are walked as quads (two triangles each), so diagonals are never
+/// drawn. Axis-aligned quads spanning several voxels, like greedy-merged
+/// faces, get a line along every voxel edge; other quads are outlined.
+pub fn draw_debug_wireframe(
+    mut gizmos: Gizmos,
+    meshes: Res<Assets<Mesh>>,
+    instances: Query<(&Handle<Mesh>, &GlobalTransform, &InheritedVisibility), With<crate::voxel::VoxelInstance>>,
+) {
+    let mut budget = MAX_WIREFRAME_LINES;
+    for (handle, transform, visibility) in &instances {
+        if !visibility.get() {
+            continue;
+        }
+        let Some(mesh) = meshes.get(handle) else { continue };
+        let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else { continue };
+        let Some(indices) = mesh.indices() else { continue };
+
+        let indices: Vec<usize> = indices.iter().collect();
+        for quad in indices.chunks_exact(6) {
+            let Some(outline) = quad_outline(quad) else { continue };
+            let corners = outline.map(|i| Vec3::from(positions[i]));
+            voxel_edges(corners, |a, b| {
+                if budget > 0 {
+                    budget -= 1;
+                    gizmos.line(transform.transform_point(a), transform.transform_point(b), Color::WHITE);
+                }
+            });
+        }
+        if budget == 0 {
+            return;
+        }
+    }
+}
+
+/// Corners of a quad emitted as two triangles, in order around its outline
+fn quad_outline(quad: &[usize]) -> Option<[usize; 4]> {
+    let (first, second) = quad.split_at(3);
+    let own = first.iter().copied().find(|i| !second.contains(i))?;
+    let other = second.iter().copied().find(|i| !first.contains(i))?;
+    let mut diagonal = first.iter().copied().filter(|i| second.contains(i));
+    Some([diagonal.next()?, own, diagonal.next()?, other])
+}
+
+/// Lines along the voxel edges of a quad given by its outline corners
+fn voxel_edges(corners: [Vec3; 4], mut line: impl FnMut(Vec3, Vec3)) {
+    let [origin, _, _, _] = corners;
+    let (u, v) = (corners[1] - origin, corners[3] - origin);
+    // Voxels are one unit wide in mesh space
+    let steps = |edge: Vec3| {
+        let axis_aligned = edge.cmpne(Vec3::ZERO).bitmask().count_ones() == 1;
+        if axis_aligned { edge.length().round().max(1.0) as usize } else { 1 }
+    };
+    let (u_steps, v_steps) = (steps(u), steps(v));
+    for i in 0..=u_steps {
+        let start = origin + u * (i as f32 / u_steps as f32);
+        line(start, start + v);
+    }
+    for j in 0..=v_steps {
+        let start = origin + v * (j as f32 / v_steps as f32);
+        line(start, start + u);
+    }
+}
+
 /// Update notification timer
 pub fn update_debug_notification(
     time: Res<Time>,
@@ -177,6 +404,29 @@ mod tests {
     use super::*;
     use crate::metrics::PerformanceMetrics;
 
+    #[test]
+    fn test_wireframe_draws_voxel_edges_without_diagonals() {
+        // One color, so every face merges into a single 2x2 quad
+        let voxels: Vec<Voxel> = (0..8u16)
+            .map(|i| Voxel { position: [i & 1, i >> 1 & 1, i >> 2], color: [200, 200, 200, 255], material_id: 0 })
+            .collect();
+        let mesh = crate::voxel::greedy::ChunkedVoxels::new(&voxels).mesh_chunk(IVec3::ZERO);
+        let mut lines = Vec::new();
+        for quad in mesh.indices.chunks_exact(6) {
+            let quad: Vec<usize> = quad.iter().map(|&i| i as usize).collect();
+            let outline = quad_outline(&quad).unwrap();
+            voxel_edges(outline.map(|i| Vec3::from(mesh.positions[i])), |a, b| lines.push((a, b)));
+        }
+
+        // Six 2x2 faces, three lines across each axis of every face
+        assert_eq!(lines.len(), 6 * 6);
+        for (a, b) in lines {
+            let delta = b - a;
+            assert_eq!(delta.cmpne(Vec3::ZERO).bitmask().count_ones(), 1, "diagonal from {} to {}", a, b);
+            assert_eq!(delta.length(), 2.0);
+        }
+    }
+
     #[test]
     fn test_ring_buffer_capacity() {
         let mut app = App::new();
@@ -198,6 +448,52 @@ mod tests {
         // However, we can verify it doesn't panic or crash.
     }
 
+    #[test]
+    fn test_f4_cycles_modes_and_coloring() {
+        let mut app = App::new();
+        app.init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<DebugState>()
+            .init_resource::<DebugColoring>()
+            .add_systems(Update, cycle_visualization_mode);
+
+        let mut seen = Vec::new();
+        for _ in 0..6 {
+            let mut keyboard = app.world.resource_mut::<ButtonInput<KeyCode>>();
+            keyboard.release(KeyCode::F4);
+            keyboard.clear();
+            keyboard.press(KeyCode::F4);
+            app.update();
+            seen.push((app.world.resource::<DebugState>().visualization_mode, *app.world.resource::<DebugColoring>()));
+        }
+
+        assert_eq!(seen[3], (VisualizationMode::MaterialId, DebugColoring::MaterialId));
+        assert_eq!(seen[4], (VisualizationMode::Normals, DebugColoring::Normals));
+        assert_eq!(seen[5], (VisualizationMode::Normal, DebugColoring::None));
+        assert!(app.world.resource::<DebugState>().notification.is_some());
+    }
+
+    #[test]
+    fn test_debug_recoloring() {
+        let voxel = Voxel { position: [0, 0, 0], color: [10, 20, 30, 128], material_id: 4 };
+        assert_eq!(DebugColoring::None.voxel_color(&voxel), voxel.color);
+        assert_eq!(DebugColoring::MaterialId.voxel_color(&voxel)[3], 128);
+        assert_ne!(
+            DebugColoring::MaterialId.voxel_color(&voxel),
+            DebugColoring::MaterialId.voxel_color(&Voxel { material_id: 5, ..voxel })
+        );
+
+        let mut mesh = VoxelMeshData {
+            positions: vec![[0.0; 3]; 3],
+            normals: vec![[0.0, 1.0, 0.0]; 3],
+            colors: vec![[1.0, 1.0, 1.0, 0.5]; 3],
+            indices: vec![0, 1, 2],
+        };
+        DebugColoring::Normals.recolor_mesh(&mut mesh);
+        assert_eq!(mesh.colors[0], [0.5, 1.0, 0.5, 0.5]);
+        DebugColoring::Performance.recolor_mesh(&mut mesh);
+        assert_eq!(mesh.colors[0][1], 1.0 - 1.0 / PERFORMANCE_TRIANGLE_BUDGET as f32);
+    }
+
     #[test]
     fn test_notification_timer() {
         let mut app = App::new();
@@ -105,6 +105,9 @@ pub fn render_hud(
             };
             ui.label(format!("Task/Mesh Shaders: {}", task_mesh_status));
 
+            ui.add_space(10.0);
+            ui.label(format!("View (F4): {:?}", debug_state.visualization_mode));
+
             // Notification section
             if let Some((message, _)) = &debug_state.notification {
                 ui.add_space(10.0);
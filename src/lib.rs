@@ -10,6 +10,7 @@ pub mod capabilities;
 pub mod debug;
 pub mod hud;
 pub mod metrics;
+pub mod nav;
 pub mod platform;
 pub mod plugin;
 pub mod postfx;
@@ -11,7 +11,8 @@ pub struct PerformanceMetrics {
     pub fps: f32,
     /// Frame time in milliseconds
     pub frame_time_ms: f32,
-    /// Total voxel count in scene
+    /// Total voxel count across resident scenes (same as `total_voxel_count`)
+    #[deprecated(note = "use `total_voxel_count`")]
     pub voxel_count: usize,
     /// Voxel render pass time (ms)
     pub voxel_pass_ms: f32,
@@ -23,20 +24,20 @@ pub struct PerformanceMetrics {
     pub gi_pass_ms: f32,
     /// NRC pass time (ms) - Professional only
     pub nrc_pass_ms: f32,
-    /// Total voxels in all scenes
+    /// Total voxels in all resident scenes (streamed-out regions excluded)
     pub total_voxel_count: usize,
-    /// Visible voxels after culling - Professional only
+    /// Voxels in chunks inside a camera frustum
     pub visible_voxel_count: usize,
-    /// Culled voxels - Professional only
+    /// Voxels in chunks hidden by frustum culling
     pub culled_voxel_count: usize,
+    /// Rendered chunks per LOD level (index 0 = full detail)
+    pub lod_distribution: [usize; crate::voxel::lod::MAX_LOD as usize + 1],
 }
 
 /// Update performance metrics each frame
 pub fn update_performance_metrics(
     diagnostics: Res<DiagnosticsStore>,
     mut metrics: ResMut<PerformanceMetrics>,
-    voxel_query: Query<&Handle<crate::voxel::VoxelScene>>,
-    scenes: Res<Assets<crate::voxel::VoxelScene>>,
 ) {
     // Update FPS
     if let Some(fps_diagnostic) = diagnostics.get(&FrameTimeDiagnosticsPlugin::FPS) {
@@ -52,12 +53,7 @@ pub fn update_performance_metrics(
         }
     }
     
-    // Count voxels across all scenes
-    metrics.total_voxel_count = voxel_query
-        .iter()
-        .filter_map(|h| scenes.get(h))
-        .map(|s| s.voxel_count())
-        .sum();
+    // Voxel totals come from `count_scene_voxels`
     
     // Per-pass timing will be added in Professional edition with GPU timestamps
     // For now, voxel_pass_ms is approximate based on frame time
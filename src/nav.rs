@@ -0,0 +1,397 @@
+// SPDX-License-Identifier: MIT
+//! Voxel navigation grid and A* pathfinding
+//!
+//! A cell is walkable when the voxel below it is solid and the cell itself
+//! plus `headroom - 1` cells above it are empty. Agents move between
+//! horizontally adjacent walkable cells, climbing or dropping at most
+//! `max_step` cells per move.
+
+use bevy::prelude::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::voxel::VoxelScene;
+
+/// Cost of one horizontal move
+const MOVE_COST: u32 = 10;
+/// Extra cost per cell climbed or dropped
+const STEP_COST: u32 = 5;
+
+/// Horizontal move directions (4-connected)
+const DIRECTIONS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+/// Agent dimensions used to extract walkable cells
+///
+/// Add to an entity holding a `Handle<VoxelScene>` to have a [`NavGrid`] built for it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NavGridConfig {
+    /// Number of empty cells required above the floor
+    pub headroom: u16,
+    /// Maximum number of cells an agent can climb or drop in one move
+    pub max_step: u16,
+}
+
+impl Default for NavGridConfig {
+    fn default() -> Self {
+        Self {
+            headroom: 2,
+            max_step: 1,
+        }
+    }
+}
+
+/// Walkable cells extracted from a voxel scene
+///
+/// World positions passed to and returned from the grid are in the scene
+/// root's local space: the scene origin plus grid position times voxel size.
+#[derive(Component, Debug, Clone, Default)]
+pub struct NavGrid {
+    config: NavGridConfig,
+    origin: Vec3,
+    voxel_size: f32,
+    solid: HashSet<IVec3>,
+    walkable: HashSet<IVec3>,
+}
+
+impl NavGrid {
+    /// Build a navigation grid from all voxels in a scene
+    pub fn build(scene: &VoxelScene, config: NavGridConfig) -> Self {
+        let solid: HashSet<IVec3> = scene.voxels().iter().map(|v| grid_pos(v.position)).collect();
+
+        let mut grid = Self {
+            config,
+            origin: scene.metadata.origin,
+            voxel_size: 1.0,
+            solid,
+            walkable: HashSet::new(),
+        };
+
+        // Only cells directly above a solid voxel can be walkable
+        let candidates: Vec<IVec3> = grid.solid.iter().map(|&p| p + IVec3::Y).collect();
+        for cell in candidates {
+            grid.refresh_cell(cell);
+        }
+
+        grid
+    }
+
+    /// Set the edge length of one cell in world units
+    pub fn with_voxel_size(mut self, voxel_size: f32) -> Self {
+        self.voxel_size = voxel_size;
+        self
+    }
+
+    /// Configuration the grid was built with
+    pub fn config(&self) -> NavGridConfig {
+        self.config
+    }
+
+    /// Whether an agent can stand in the given cell
+    pub fn is_walkable(&self, cell: IVec3) -> bool {
+        self.walkable.contains(&cell)
+    }
+
+    /// Number of walkable cells
+    pub fn walkable_count(&self) -> usize {
+        self.walkable.len()
+    }
+
+    /// Grid cell containing a world position
+    pub fn cell_at(&self, world: Vec3) -> IVec3 {
+        ((world - self.origin) / self.voxel_size).round().as_ivec3()
+    }
+
+    /// World position of a cell center
+    pub fn cell_center(&self, cell: IVec3) -> Vec3 {
+        self.origin + cell.as_vec3() * self.voxel_size
+    }
+
+    /// Re-extract walkable cells after voxels inside `min..=max` changed
+    ///
+    /// Only the columns covering the edited region are re-evaluated.
+    pub fn update_region(&mut self, scene: &VoxelScene, min: [u16; 3], max: [u16; 3]) {
+        let min = grid_pos(min);
+        let max = grid_pos(max);
+        let in_region = |p: &IVec3| p.cmpge(min).all() && p.cmple(max).all();
+
+        self.solid.retain(|p| !in_region(p));
+        self.solid.extend(
+            scene
+                .voxels()
+                .iter()
+                .map(|v| grid_pos(v.position))
+                .filter(|p| in_region(p)),
+        );
+
+        // A cell depends on the voxel below it and `headroom` cells from itself upwards
+        let y_min = min.y - self.config.headroom as i32 + 1;
+        let y_max = max.y + 1;
+        for x in min.x..=max.x {
+            for z in min.z..=max.z {
+                for y in y_min..=y_max {
+                    self.refresh_cell(IVec3::new(x, y, z));
+                }
+            }
+        }
+    }
+
+    /// Find a path between two world positions
+    ///
+    /// Returns the world-space centers of the cells along the path, including
+    /// both endpoints. Positions are snapped to the nearest walkable cell in
+    /// their column within `max_step`.
+    pub fn find_path(&self, start: Vec3, goal: Vec3) -> Option<Vec<Vec3>> {
+        let start = self.snap_to_walkable(self.cell_at(start))?;
+        let goal = self.snap_to_walkable(self.cell_at(goal))?;
+
+        self.find_cell_path(start, goal)
+            .map(|cells| cells.into_iter().map(|c| self.cell_center(c)).collect())
+    }
+
+    /// Run A* between two walkable cells
+    pub fn find_cell_path(&self, start: IVec3, goal: IVec3) -> Option<Vec<IVec3>> {
+        if !self.is_walkable(start) || !self.is_walkable(goal) {
+            return None;
+        }
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<IVec3, IVec3> = HashMap::new();
+        let mut best_cost: HashMap<IVec3, u32> = HashMap::new();
+
+        best_cost.insert(start, 0);
+        open.push(Reverse((heuristic(start, goal), 0u32, start.to_array())));
+
+        while let Some(Reverse((_, cost, cell))) = open.pop() {
+            let cell = IVec3::from_array(cell);
+            if cell == goal {
+                return Some(reconstruct_path(&came_from, goal));
+            }
+
+            // Skip stale heap entries
+            if best_cost.get(&cell).is_some_and(|&c| cost > c) {
+                continue;
+            }
+
+            for (next, move_cost) in self.neighbors(cell) {
+                let next_cost = cost + move_cost;
+                let improved = match best_cost.get(&next) {
+                    Some(&c) => next_cost < c,
+                    None => true,
+                };
+                if improved {
+                    best_cost.insert(next, next_cost);
+                    came_from.insert(next, cell);
+                    open.push(Reverse((next_cost + heuristic(next, goal), next_cost, next.to_array())));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn is_solid(&self, cell: IVec3) -> bool {
+        self.solid.contains(&cell)
+    }
+
+    fn refresh_cell(&mut self, cell: IVec3) {
+        let floor = self.is_solid(cell - IVec3::Y);
+        let clear = (0..self.config.headroom as i32).all(|k| !self.is_solid(cell + IVec3::Y * k));
+
+        if floor && clear {
+            self.walkable.insert(cell);
+        } else {
+            self.walkable.remove(&cell);
+        }
+    }
+
+    fn snap_to_walkable(&self, cell: IVec3) -> Option<IVec3> {
+        let reach = self.config.max_step as i32;
+        (0..=reach)
+            .flat_map(|d| [cell - IVec3::Y * d, cell + IVec3::Y * d])
+            .find(|c| self.is_walkable(*c))
+    }
+
+    fn neighbors(&self, cell: IVec3) -> impl Iterator<Item = (IVec3, u32)> + '_ {
+        let step = self.config.max_step as i32;
+        let headroom = self.config.headroom as i32;
+
+        DIRECTIONS.iter().flat_map(move |&dir| {
+            (-step..=step).filter_map(move |dy| {
+                let next = cell + dir + IVec3::Y * dy;
+                if !self.is_walkable(next) {
+                    return None;
+                }
+
+                // Climbing needs clearance above the current cell,
+                // dropping needs clearance above the target cell
+                let (column, rise) = if dy > 0 { (cell, dy) } else { (next, -dy) };
+                let blocked = (0..rise).any(|k| self.is_solid(column + IVec3::Y * (headroom + k)));
+                if blocked {
+                    return None;
+                }
+
+                Some((next, MOVE_COST + STEP_COST * dy.unsigned_abs()))
+            })
+        })
+    }
+}
+
+/// Filter for scene entities whose grid inputs changed
+type NavInputsChanged = Or<(
+    Changed<NavGridConfig>,
+    Changed<Handle<VoxelScene>>,
+    Changed<crate::voxel::VoxelSize>,
+)>;
+
+/// Build or rebuild navigation grids for scenes with a [`NavGridConfig`]
+///
+/// Voxel edit commands update existing grids in place; other scene changes
+/// trigger a full rebuild.
+pub fn build_nav_grids(
+    mut commands: Commands,
+    scenes: Res<Assets<VoxelScene>>,
+    mut asset_events: EventReader<AssetEvent<VoxelScene>>,
+    mut voxel_edits: EventReader<crate::voxel::VoxelsChanged>,
+    edit_tracker: Res<crate::voxel::VoxelEditTracker>,
+    changed: Query<Entity, NavInputsChanged>,
+    all: Query<(Entity, &Handle<VoxelScene>, &NavGridConfig, Option<&crate::voxel::VoxelSize>)>,
+    mut grids: Query<(&Handle<VoxelScene>, &mut NavGrid)>,
+) {
+    let mut to_rebuild: HashSet<Entity> = changed.iter().collect();
+
+    for event in asset_events.read() {
+        if let AssetEvent::Modified { id } = event {
+            if edit_tracker.is_edit(*id) {
+                continue;
+            }
+        }
+        if let AssetEvent::Added { id } | AssetEvent::Modified { id } = event {
+            to_rebuild.extend(
+                all.iter()
+                    .filter(|(_, handle, _, _)| handle.id() == *id)
+                    .map(|(entity, _, _, _)| entity),
+            );
+        }
+    }
+
+    for change in voxel_edits.read() {
+        let Some(scene) = scenes.get(change.scene) else { continue };
+        for (handle, mut grid) in &mut grids {
+            if handle.id() == change.scene {
+                grid.update_region(scene, change.min, change.max);
+            }
+        }
+    }
+
+    for entity in to_rebuild {
+        let Ok((_, handle, config, size)) = all.get(entity) else { continue };
+        let Some(scene) = scenes.get(handle) else { continue };
+
+        let voxel_size = scene.voxel_size(size).0;
+        let grid = NavGrid::build(scene, *config).with_voxel_size(voxel_size);
+        debug!("Built nav grid for {}: {} walkable cells", scene.metadata.name, grid.walkable_count());
+        commands.entity(entity).insert(grid);
+    }
+}
+
+fn grid_pos(position: [u16; 3]) -> IVec3 {
+    IVec3::new(position[0] as i32, position[1] as i32, position[2] as i32)
+}
+
+fn heuristic(from: IVec3, to: IVec3) -> u32 {
+    let d = (to - from).abs();
+    MOVE_COST * (d.x + d.z) as u32 + STEP_COST * d.y as u32
+}
+
+fn reconstruct_path(came_from: &HashMap<IVec3, IVec3>, goal: IVec3) -> Vec<IVec3> {
+    let mut path = vec![goal];
+    let mut current = goal;
+    while let Some(&prev) = came_from.get(&current) {
+        path.push(prev);
+        current = prev;
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::Voxel;
+
+    fn floor(size: u16) -> Vec<[u16; 3]> {
+        (0..size).flat_map(|x| (0..size).map(move |z| [x, 0, z])).collect()
+    }
+
+    #[test]
+    fn test_flat_floor_is_walkable() {
+        let grid = NavGrid::build(&VoxelScene::from_positions(floor(5)), NavGridConfig::default());
+        assert_eq!(grid.walkable_count(), 25);
+        assert!(grid.is_walkable(IVec3::new(2, 1, 2)));
+        assert!(!grid.is_walkable(IVec3::new(2, 0, 2)));
+    }
+
+    #[test]
+    fn test_headroom_blocks_low_ceiling() {
+        let mut voxels = floor(3);
+        voxels.push([1, 2, 1]); // Ceiling one cell above the floor
+        let grid = NavGrid::build(&VoxelScene::from_positions(voxels), NavGridConfig::default());
+        assert!(!grid.is_walkable(IVec3::new(1, 1, 1)));
+        assert!(grid.is_walkable(IVec3::new(0, 1, 0)));
+    }
+
+    #[test]
+    fn test_path_across_floor() {
+        let grid = NavGrid::build(&VoxelScene::from_positions(floor(5)), NavGridConfig::default());
+        let path = grid.find_path(Vec3::new(0.0, 1.0, 0.0), Vec3::new(4.0, 1.0, 4.0)).unwrap();
+        assert_eq!(path.len(), 9); // 8 moves on a 4-connected grid
+        assert_eq!(path[0], Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(*path.last().unwrap(), Vec3::new(4.0, 1.0, 4.0));
+    }
+
+    #[test]
+    fn test_path_scales_with_voxel_size() {
+        let grid = NavGrid::build(&VoxelScene::from_positions(floor(5)), NavGridConfig::default()).with_voxel_size(0.5);
+        let path = grid.find_path(Vec3::new(0.0, 0.5, 0.0), Vec3::new(2.0, 0.5, 2.0)).unwrap();
+        assert_eq!(path.len(), 9);
+        assert_eq!(*path.last().unwrap(), Vec3::new(2.0, 0.5, 2.0));
+    }
+
+    #[test]
+    fn test_path_respects_step_height() {
+        // Floor at y=0 for x<3, a 1-high step to y=1, then a wall two cells high at x=5
+        let mut voxels = floor(8);
+        voxels.extend((3..8).flat_map(|x| (0..8).map(move |z| [x, 1, z])));
+        let grid = NavGrid::build(&VoxelScene::from_positions(voxels.clone()), NavGridConfig::default());
+        let path = grid.find_cell_path(IVec3::new(0, 1, 0), IVec3::new(6, 2, 0)).unwrap();
+        assert!(path.contains(&IVec3::new(3, 2, 0)));
+
+        voxels.extend((0..8).map(|z| [5, 2, z]));
+        voxels.extend((0..8).map(|z| [5, 3, z]));
+        let grid = NavGrid::build(&VoxelScene::from_positions(voxels), NavGridConfig::default());
+        assert!(grid.find_cell_path(IVec3::new(0, 1, 0), IVec3::new(7, 2, 0)).is_none());
+    }
+
+    #[test]
+    fn test_update_region_matches_rebuild() {
+        let config = NavGridConfig::default();
+        let mut scene = VoxelScene::from_positions(floor(6));
+        let mut grid = NavGrid::build(&scene, config);
+
+        // Knock a hole in the floor and add a pillar
+        scene.remove_voxel([2, 0, 2]).unwrap();
+        scene.add_voxel(Voxel { position: [4, 1, 4], color: [0, 0, 0, 255], material_id: 0 }).unwrap();
+        grid.update_region(&scene, [2, 0, 2], [4, 1, 4]);
+
+        let rebuilt = NavGrid::build(&scene, config);
+        assert_eq!(grid.walkable, rebuilt.walkable);
+        assert!(!grid.is_walkable(IVec3::new(2, 1, 2)));
+        assert!(grid.is_walkable(IVec3::new(4, 2, 4)));
+    }
+
+    #[test]
+    fn test_no_path_to_unwalkable_goal() {
+        let grid = NavGrid::build(&VoxelScene::from_positions(floor(3)), NavGridConfig::default());
+        assert!(grid.find_cell_path(IVec3::new(0, 1, 0), IVec3::new(10, 1, 10)).is_none());
+    }
+}
@@ -2,10 +2,11 @@
 //! `HeartOn` Public Plugin - Core integration
 
 use bevy::prelude::*;
+use std::sync::Arc;
 
 /// `HeartOn` Community Edition Plugin
 ///
-/// Provides MIT-licensed voxel rendering with 10M voxel limit.
+/// Provides MIT-licensed voxel rendering (greedy-meshed chunks by default) with 10M voxel limit.
 ///
 /// # Example
 /// ```rust,no_run
@@ -27,16 +28,30 @@ use bevy::prelude::*;
 ///     ))
 ///     .run();
 /// ```
+///
+/// Alternative renderers implement [`crate::voxel::VoxelRenderBackend`] and are
+/// registered with [`HeartOnPublicPlugin::with_render_backend`].
 #[derive(Default)]
 pub struct HeartOnPublicPlugin {
     /// Configuration overrides
     pub config: crate::capabilities::CapabilityConfig,
+    /// Extra render backends, preferred over the built-in ones
+    render_backends: Vec<Arc<dyn crate::voxel::VoxelRenderBackend>>,
 }
 
 impl HeartOnPublicPlugin {
     /// Create plugin with custom configuration
     pub fn new(config: crate::capabilities::CapabilityConfig) -> Self {
-        Self { config }
+        Self { config, ..default() }
+    }
+
+    /// Register a render backend
+    ///
+    /// Backends are tried in registration order, before the built-in chunked
+    /// renderer; the dummy renderer is the final fallback.
+    pub fn with_render_backend(mut self, backend: impl crate::voxel::VoxelRenderBackend) -> Self {
+        self.render_backends.push(Arc::new(backend));
+        self
     }
 }
 
@@ -54,6 +69,7 @@ impl Plugin for HeartOnPublicPlugin {
         app.init_resource::<crate::capabilities::GpuCapabilities>()
             .init_resource::<crate::metrics::PerformanceMetrics>()
             .init_resource::<crate::debug::DebugState>()
+            .init_resource::<crate::debug::DebugColoring>()
             .init_resource::<crate::tier::RevenueReportingConfig>()
             .register_type::<crate::tier::RevenueReportingConfig>()
             .init_resource::<crate::config::HeartOnConfig>()
@@ -70,7 +86,19 @@ impl Plugin for HeartOnPublicPlugin {
 
         // Register assets
         app.init_asset::<crate::voxel::VoxelScene>()
-            .init_asset_loader::<crate::voxel::VoxelSceneLoader>();
+            .init_asset_loader::<crate::voxel::VoxelSceneLoader>()
+            .init_asset_loader::<crate::voxel::VoxelSceneRonLoader>()
+            .init_asset_loader::<crate::voxel::VoxLoader>();
+
+        // Voxel editing
+        app.add_event::<crate::voxel::VoxelEditCommand>()
+            .add_event::<crate::voxel::VoxelsChanged>()
+            .add_event::<crate::voxel::TierLimitExceeded>()
+            .init_resource::<crate::voxel::VoxelEditTracker>()
+            .init_resource::<crate::voxel::VoxelMaterialCache>()
+            .init_resource::<crate::voxel::VoxelMaterialRegistry>()
+            .init_resource::<crate::voxel::VoxelChunkBounds>()
+            .configure_sets(Update, (crate::voxel::VoxelSet::Edit, crate::voxel::VoxelSet::Sync).chain());
 
         // Add Egui Plugin
         if !app.is_plugin_added::<bevy_egui::EguiPlugin>() {
@@ -80,9 +108,35 @@ impl Plugin for HeartOnPublicPlugin {
         // Insert config as resource for systems to access
         app.insert_resource(self.config.clone());
 
+        // First systems
+        app.add_systems(First, crate::voxel::edit::rotate_edit_tracker);
+
+        // PostUpdate systems (after despawned instances released their handles)
+        app.add_systems(PostUpdate, crate::voxel::material_cache::purge_voxel_material_cache);
+
+        // Frustum culling needs current transforms and frusta, and must finish before visibility propagates
+        {
+            use bevy::render::view::VisibilitySystems;
+            app.add_systems(
+                PostUpdate,
+                crate::voxel::culling::cull_voxel_chunks
+                    .after(bevy::transform::TransformSystem::TransformPropagate)
+                    .after(VisibilitySystems::UpdateOrthographicFrusta)
+                    .after(VisibilitySystems::UpdatePerspectiveFrusta)
+                    .after(VisibilitySystems::UpdateProjectionFrusta)
+                    .before(VisibilitySystems::VisibilityPropagate),
+            );
+        }
+
         // Startup systems
         app.add_systems(Startup, crate::capabilities::detect_gpu_capabilities)
-            .add_systems(Startup, apply_capability_overrides.after(crate::capabilities::detect_gpu_capabilities));
+            .add_systems(Startup, apply_capability_overrides.after(crate::capabilities::detect_gpu_capabilities))
+            .add_systems(Startup, crate::voxel::backend::select_voxel_backend.after(apply_capability_overrides));
+
+        // Render backends
+        let mut backends = self.render_backends.clone();
+        backends.push(Arc::new(crate::voxel::ChunkedVoxelBackend));
+        crate::voxel::backend::register_backends(app, &backends);
 
         // Update systems
         app.add_systems(
@@ -91,14 +145,39 @@ impl Plugin for HeartOnPublicPlugin {
                 crate::metrics::update_performance_metrics,
                 crate::budget::check_budgets.after(crate::metrics::update_performance_metrics),
                 crate::debug::toggle_debug_hud,
+                crate::debug::cycle_visualization_mode,
                 crate::debug::update_debug_notification,
-                crate::debug::export_performance_csv.after(crate::metrics::update_performance_metrics),
-                crate::hud::render_hud.after(crate::metrics::update_performance_metrics),
-                crate::voxel::check_voxel_limits,
-                crate::voxel::dummy_renderer::render_dummy_voxels,
+                crate::debug::export_performance_csv
+                    .after(crate::metrics::update_performance_metrics)
+                    .after(crate::voxel::count_scene_voxels),
+                crate::hud::render_hud
+                    .after(crate::metrics::update_performance_metrics)
+                    .after(crate::voxel::count_scene_voxels),
+                crate::voxel::count_scene_voxels.in_set(crate::voxel::VoxelSet::Sync),
+                crate::voxel::check_voxel_limits.after(crate::voxel::count_scene_voxels),
+                crate::voxel::edit::apply_voxel_edits.in_set(crate::voxel::VoxelSet::Edit),
                 crate::voxel::dummy_renderer::cleanup_voxel_instances,
+                crate::nav::build_nav_grids.in_set(crate::voxel::VoxelSet::Sync),
+                crate::voxel::lights::sync_voxel_lights.in_set(crate::voxel::VoxelSet::Sync),
+                crate::voxel::streaming::init_streamed_worlds,
+                crate::voxel::streaming::stream_regions.after(crate::voxel::streaming::init_streamed_worlds),
             ),
         );
+
+        // Debug gizmos (only when the app has a gizmo pipeline)
+        {
+            use crate::debug::{visualization_mode_is, VisualizationMode};
+            use bevy::render::view::VisibilitySystems;
+            app.add_systems(
+                PostUpdate,
+                (
+                    crate::debug::draw_debug_bounds.run_if(visualization_mode_is(VisualizationMode::Bounds)),
+                    crate::debug::draw_debug_wireframe.run_if(visualization_mode_is(VisualizationMode::Wireframe)),
+                )
+                    .after(VisibilitySystems::VisibilityPropagate)
+                    .run_if(resource_exists::<bevy::gizmos::config::GizmoConfigStore>),
+            );
+        }
     }
 }
 
@@ -148,4 +227,22 @@ mod tests {
         
         assert!(app.world.contains_resource::<crate::capabilities::CapabilityConfig>());
     }
+
+    #[test]
+    fn test_rendering_path_selects_backend() {
+        use crate::capabilities::{CapabilityConfig, RenderingPath};
+        use crate::voxel::{ActiveVoxelBackend, ChunkedVoxelBackend, DummyVoxelBackend};
+
+        let active = |path| {
+            let mut app = App::new();
+            app.add_plugins(bevy::asset::AssetPlugin::default())
+                .init_asset::<Shader>()
+                .add_plugins(HeartOnPublicPlugin::new(CapabilityConfig::default().with_rendering_path(path)));
+            app.world.run_schedule(Startup);
+            app.world.resource::<ActiveVoxelBackend>().0
+        };
+
+        assert_eq!(active(RenderingPath::ComputeIndirect), ChunkedVoxelBackend::NAME);
+        assert_eq!(active(RenderingPath::TaskMesh), DummyVoxelBackend::NAME);
+    }
 }
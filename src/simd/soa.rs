@@ -2,7 +2,7 @@
 //! Structure-of-Arrays (SoA) types for SIMD operations.
 
 use bevy::prelude::*;
-use bevy::render::primitives::Aabb;
+use bevy::render::primitives::{Aabb, Frustum};
 use super::types::SimdF32x4;
 
 /// 4 Vectors stored in SoA format (xxxx, yyyy, zzzz).
@@ -98,4 +98,29 @@ impl SimdAabbX4 {
         }
         mask
     }
+
+    /// Checks the 4 AABBs against a view frustum.
+    /// Returns a bitmask (0-15) where bit i is set if AABB i is at least partially inside.
+    pub fn intersects_frustum(&self, frustum: &Frustum) -> u8 {
+        let mut mask = 0b1111u8;
+        for half_space in &frustum.half_spaces {
+            let plane = half_space.normal_d();
+
+            // The corner farthest along the plane normal is the same for every lane
+            let px = if plane.x >= 0.0 { self.max_x } else { self.min_x };
+            let py = if plane.y >= 0.0 { self.max_y } else { self.min_y };
+            let pz = if plane.z >= 0.0 { self.max_z } else { self.min_z };
+
+            let distance = px * SimdF32x4::splat(plane.x)
+                + py * SimdF32x4::splat(plane.y)
+                + pz * SimdF32x4::splat(plane.z)
+                + SimdF32x4::splat(plane.w);
+
+            mask &= distance.ge_mask(SimdF32x4::splat(0.0));
+            if mask == 0 {
+                break;
+            }
+        }
+        mask
+    }
 }
@@ -42,6 +42,15 @@ impl SimdF32x4 {
             self.0[3].max(other.0[3]),
         ])
     }
+
+    /// Returns a bitmask where bit i is set if lane i of self >= other.
+    #[inline(always)]
+    pub fn ge_mask(self, other: Self) -> u8 {
+        (self.0[0] >= other.0[0]) as u8
+            | ((self.0[1] >= other.0[1]) as u8) << 1
+            | ((self.0[2] >= other.0[2]) as u8) << 2
+            | ((self.0[3] >= other.0[3]) as u8) << 3
+    }
 }
 
 impl Add for SimdF32x4 {
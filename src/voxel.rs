@@ -1,28 +1,183 @@
 // SPDX-License-Identifier: MIT
 //! Voxel management and validation
 
+pub mod backend;
+pub mod chunk_renderer;
+pub mod culling;
 pub mod dummy_renderer;
+pub mod edit;
+pub mod export;
+pub mod greedy;
+pub mod hvox;
+pub mod hvox_ron;
+pub mod hvox_stream;
+pub mod import;
+pub mod lights;
 pub mod loader;
+pub mod lod;
+pub mod material_cache;
+pub mod material_registry;
+pub mod mesh_data;
 pub mod scene;
+pub mod streaming;
+pub mod surface;
+pub mod vox;
 
-pub use dummy_renderer::{VoxelInstance, VoxelSceneRoot};
-pub use loader::VoxelSceneLoader;
-pub use scene::{VoxelScene, VoxelMetadata, VoxelData, CommunityVoxelData, ProfessionalVoxelData, Voxel, VoxelError};
+pub use backend::{
+    ActiveVoxelBackend, ChunkedVoxelBackend, DummyVoxelBackend, VoxelBackendSet, VoxelRenderBackend,
+    VoxelRenderBackends,
+};
+pub use chunk_renderer::VoxelChunk;
+pub use culling::VoxelChunkBounds;
+pub use dummy_renderer::{VoxelCell, VoxelInstance, VoxelSceneRoot, VoxelSize};
+pub use edit::{VoxelEditCommand, VoxelEditTracker, VoxelsChanged};
+pub use hvox_stream::{HvoxStreamParser, HvoxView};
+pub use hvox_ron::VoxelSceneRonLoader;
+pub use import::{ColorRamp, HeightmapSettings, VoxelImportError};
+pub use lights::{EmissiveCluster, VoxelLight};
+pub use loader::{
+    HvoxErrorKind, HvoxFormatError, TierLimitPolicy, VoxelSceneLoader, VoxelSceneLoaderSettings, VoxelValidation,
+};
+pub use lod::VoxelLodState;
+pub use material_cache::VoxelMaterialCache;
+pub use material_registry::{VoxelMaterialDef, VoxelMaterialRegistry, VoxelPass};
+pub use mesh_data::VoxelMeshData;
+pub use scene::{
+    CommunityVoxelData, MetadataValue, ProfessionalVoxelData, Voxel, VoxelData, VoxelError, VoxelMarker, VoxelMetadata,
+    VoxelScene,
+};
+pub use streaming::{StreamedRegion, StreamedVoxelWorld, StreamingFocus, StreamingSource};
+pub use surface::{surface_nets, VoxelMeshMode};
+pub use vox::VoxLoader;
 
 use bevy::prelude::*;
 
-/// Check that voxel count doesn't exceed tier limits
+/// System sets for voxel scene processing, run in `Update` in this order
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum VoxelSet {
+    /// Apply queued `VoxelEditCommand`s
+    Edit,
+    /// React to scene and voxel changes (rendering, navigation)
+    Sync,
+}
+
+/// Number of voxels in the scene an entity holds, kept up to date each frame
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SceneVoxelCount(pub usize);
+
+/// Sent when the resident voxel total rises above the tier limit
+///
+/// Fired once per crossing; the total has to drop back under the limit
+/// before it fires again.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TierLimitExceeded {
+    /// Tier whose limit was exceeded
+    pub tier: crate::tier::Tier,
+    /// Voxels in all resident scenes
+    pub total: usize,
+    /// Tier voxel limit
+    pub limit: usize,
+}
+
+/// Update per-scene voxel counts and the world total
+pub fn count_scene_voxels(
+    mut commands: Commands,
+    scenes: Res<Assets<VoxelScene>>,
+    mut scene_entities: Query<(Entity, &Handle<VoxelScene>, Option<&mut SceneVoxelCount>)>,
+    mut metrics: ResMut<crate::metrics::PerformanceMetrics>,
+) {
+    let mut total = 0;
+    for (entity, handle, count) in &mut scene_entities {
+        // Scenes still loading count as empty
+        let voxels = scenes.get(handle).map_or(0, |scene| scene.voxel_count());
+        total += voxels;
+        match count {
+            Some(mut count) => {
+                count.set_if_neq(SceneVoxelCount(voxels));
+            }
+            None => {
+                commands.entity(entity).insert(SceneVoxelCount(voxels));
+            }
+        }
+    }
+    metrics.total_voxel_count = total;
+    #[allow(deprecated)]
+    {
+        metrics.voxel_count = total;
+    }
+}
+
+/// Check that resident voxel count doesn't exceed tier limits
 pub fn check_voxel_limits(
     metrics: Res<crate::metrics::PerformanceMetrics>,
+    mut exceeded_events: EventWriter<TierLimitExceeded>,
+    mut exceeded: Local<bool>,
 ) {
     let max_voxels = crate::tier::max_voxels();
-    
-    if metrics.voxel_count > max_voxels {
+    let over = metrics.total_voxel_count > max_voxels;
+
+    if over && !*exceeded {
         warn!(
             "Voxel count ({}) exceeds {} tier limit ({}). Consider upgrading to unlock more voxels.",
-            metrics.voxel_count,
+            metrics.total_voxel_count,
             crate::tier::current_tier().name(),
             max_voxels
         );
+        exceeded_events.send(TierLimitExceeded {
+            tier: crate::tier::current_tier(),
+            total: metrics.total_voxel_count,
+            limit: max_voxels,
+        });
+    }
+    *exceeded = over;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_tracked_per_scene_and_summed() {
+        let mut app = App::new();
+        app.add_plugins(bevy::asset::AssetPlugin::default())
+            .init_asset::<VoxelScene>()
+            .init_resource::<crate::metrics::PerformanceMetrics>()
+            .add_systems(Update, count_scene_voxels);
+
+        let mut assets = app.world.resource_mut::<Assets<VoxelScene>>();
+        let small = assets.add(VoxelScene::test_cube(2));
+        let large = assets.add(VoxelScene::test_cube(3));
+        let a = app.world.spawn(small).id();
+        let b = app.world.spawn(large).id();
+        app.update();
+
+        assert_eq!(app.world.get::<SceneVoxelCount>(a), Some(&SceneVoxelCount(8)));
+        assert_eq!(app.world.get::<SceneVoxelCount>(b), Some(&SceneVoxelCount(27)));
+        assert_eq!(app.world.resource::<crate::metrics::PerformanceMetrics>().total_voxel_count, 35);
+    }
+
+    #[test]
+    fn test_limit_event_fires_once_per_crossing() {
+        let mut app = App::new();
+        app.init_resource::<crate::metrics::PerformanceMetrics>()
+            .add_event::<TierLimitExceeded>()
+            .add_systems(Update, check_voxel_limits);
+        let limit = crate::tier::max_voxels();
+        let set_total = |app: &mut App, total: usize| {
+            app.world.resource_mut::<crate::metrics::PerformanceMetrics>().total_voxel_count = total;
+            app.update();
+            app.world.resource_mut::<Events<TierLimitExceeded>>().drain().collect::<Vec<_>>()
+        };
+
+        // Studio has no limit to cross
+        if limit == usize::MAX {
+            return;
+        }
+        let over = limit + 1;
+        let events = set_total(&mut app, over);
+        assert_eq!(events, [TierLimitExceeded { tier: crate::tier::current_tier(), total: over, limit }]);
+        assert!(set_total(&mut app, over).is_empty());
+        assert!(set_total(&mut app, 0).is_empty());
+        assert_eq!(set_total(&mut app, over).len(), 1);
     }
 }
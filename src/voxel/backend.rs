@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: MIT
+//! Pluggable voxel render backends
+//!
+//! Every registered backend adds its systems at build time inside its own
+//! [`VoxelBackendSet`]. Once GPU capabilities are known, exactly one backend
+//! is selected from [`GpuCapabilities::rendering_path`](crate::capabilities::GpuCapabilities)
+//! and only its set runs. The per-voxel dummy renderer is always registered
+//! last as the fallback.
+
+use bevy::prelude::*;
+use std::sync::Arc;
+
+use crate::capabilities::{GpuCapabilities, RenderingPath};
+
+/// A voxel renderer that can be registered on `HeartOnPublicPlugin`
+///
+/// Render entities should carry [`super::VoxelInstance`] so scene cleanup and
+/// the material cache keep working regardless of the active backend.
+pub trait VoxelRenderBackend: Send + Sync + 'static {
+    /// Unique backend name
+    fn name(&self) -> &'static str;
+
+    /// Whether this backend can render on the given path
+    fn supports(&self, path: RenderingPath) -> bool;
+
+    /// Register the backend's systems, all inside `set`
+    fn build(&self, app: &mut App, set: VoxelBackendSet);
+}
+
+/// System set holding one backend's systems (runs only while it is active)
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VoxelBackendSet(pub &'static str);
+
+/// Registered render backends in priority order
+#[derive(Resource, Default, Clone)]
+pub struct VoxelRenderBackends(Vec<Arc<dyn VoxelRenderBackend>>);
+
+impl VoxelRenderBackends {
+    /// Backend names in priority order
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.0.iter().map(|backend| backend.name())
+    }
+
+    /// First backend supporting `path`, or the fallback
+    pub fn select(&self, path: RenderingPath) -> &'static str {
+        self.0
+            .iter()
+            .find(|backend| backend.supports(path))
+            .map_or(DummyVoxelBackend::NAME, |backend| backend.name())
+    }
+}
+
+/// Name of the backend whose systems are running
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveVoxelBackend(pub &'static str);
+
+impl Default for ActiveVoxelBackend {
+    fn default() -> Self {
+        Self(DummyVoxelBackend::NAME)
+    }
+}
+
+/// Greedy-meshed chunk renderer (default Community Edition backend)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ChunkedVoxelBackend;
+
+impl ChunkedVoxelBackend {
+    /// Backend name
+    pub const NAME: &'static str = "chunked";
+}
+
+impl VoxelRenderBackend for ChunkedVoxelBackend {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn supports(&self, path: RenderingPath) -> bool {
+        // Meshes go through the regular vertex pipeline; task/mesh GPUs need a backend built for them
+        path == RenderingPath::ComputeIndirect
+    }
+
+    fn build(&self, app: &mut App, set: VoxelBackendSet) {
+        app.init_resource::<super::chunk_renderer::ChunkMeshJobs>()
+            .init_resource::<super::lod::VoxelLodState>()
+            .add_systems(
+                Update,
+                (
+                    super::lod::update_chunk_lods,
+                    super::chunk_renderer::render_voxel_chunks,
+                    super::chunk_renderer::apply_chunk_meshes,
+                )
+                    .chain()
+                    .in_set(set),
+            );
+    }
+}
+
+/// One cube entity per voxel (fallback backend)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DummyVoxelBackend;
+
+impl DummyVoxelBackend {
+    /// Backend name
+    pub const NAME: &'static str = "dummy";
+}
+
+impl VoxelRenderBackend for DummyVoxelBackend {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn supports(&self, _path: RenderingPath) -> bool {
+        true
+    }
+
+    fn build(&self, app: &mut App, set: VoxelBackendSet) {
+        app.add_systems(Update, super::dummy_renderer::render_dummy_voxels.in_set(set));
+    }
+}
+
+/// Register backends in priority order, followed by the dummy fallback
+///
+/// Backends whose name is already taken are skipped with a warning.
+pub fn register_backends(app: &mut App, backends: &[Arc<dyn VoxelRenderBackend>]) {
+    let fallback: Arc<dyn VoxelRenderBackend> = Arc::new(DummyVoxelBackend);
+    let mut registered = VoxelRenderBackends::default();
+
+    for backend in backends.iter().chain(std::iter::once(&fallback)) {
+        let name = backend.name();
+        if registered.names().any(|n| n == name) {
+            warn!("Voxel render backend '{}' is already registered, skipping", name);
+            continue;
+        }
+
+        let set = VoxelBackendSet(name);
+        app.configure_sets(
+            Update,
+            set.in_set(super::VoxelSet::Sync).run_if(voxel_backend_active(name)),
+        );
+        backend.build(app, set);
+        registered.0.push(backend.clone());
+    }
+
+    app.insert_resource(registered).init_resource::<ActiveVoxelBackend>();
+}
+
+/// Run condition: whether the named backend is active
+pub fn voxel_backend_active(name: &'static str) -> impl Fn(Res<ActiveVoxelBackend>) -> bool + Clone {
+    move |active: Res<ActiveVoxelBackend>| active.0 == name
+}
+
+/// Pick the backend for the detected rendering path
+pub fn select_voxel_backend(
+    backends: Res<VoxelRenderBackends>,
+    gpu_caps: Res<GpuCapabilities>,
+    mut active: ResMut<ActiveVoxelBackend>,
+) {
+    let name = backends.select(gpu_caps.rendering_path);
+    info!("Voxel render backend: {} ({:?})", name, gpu_caps.rendering_path);
+    active.0 = name;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TaskMeshOnly;
+
+    impl VoxelRenderBackend for TaskMeshOnly {
+        fn name(&self) -> &'static str {
+            "task_mesh_only"
+        }
+
+        fn supports(&self, path: RenderingPath) -> bool {
+            path == RenderingPath::TaskMesh
+        }
+
+        fn build(&self, _app: &mut App, _set: VoxelBackendSet) {}
+    }
+
+    #[test]
+    fn test_select_by_rendering_path() {
+        let backends = VoxelRenderBackends(vec![Arc::new(TaskMeshOnly), Arc::new(ChunkedVoxelBackend)]);
+        assert_eq!(backends.select(RenderingPath::TaskMesh), "task_mesh_only");
+        assert_eq!(backends.select(RenderingPath::ComputeIndirect), ChunkedVoxelBackend::NAME);
+    }
+
+    #[test]
+    fn test_falls_back_to_dummy() {
+        let backends = VoxelRenderBackends(vec![Arc::new(TaskMeshOnly)]);
+        assert_eq!(backends.select(RenderingPath::ComputeIndirect), DummyVoxelBackend::NAME);
+    }
+
+    #[test]
+    fn test_register_skips_duplicates_and_selects_on_startup() {
+        let mut app = App::new();
+        app.insert_resource(GpuCapabilities {
+            rendering_path: RenderingPath::TaskMesh,
+            ..default()
+        })
+        .add_systems(Startup, select_voxel_backend);
+        register_backends(&mut app, &[Arc::new(TaskMeshOnly), Arc::new(TaskMeshOnly)]);
+
+        let names: Vec<_> = app.world.resource::<VoxelRenderBackends>().names().collect();
+        assert_eq!(names, ["task_mesh_only", DummyVoxelBackend::NAME]);
+
+        app.update();
+        assert_eq!(app.world.resource::<ActiveVoxelBackend>().0, "task_mesh_only");
+    }
+}
@@ -0,0 +1,565 @@
+// SPDX-License-Identifier: MIT
+//! Chunked voxel renderer using greedy-meshed chunks (Community Edition)
+//!
+//! Each scene is split into `CHUNK_SIZE`³ chunks; every non-empty chunk is a
+//! single mesh entity with vertex colors and one shared material. Chunk
+//! entities are children of the scene root, placed by the scene origin and
+//! [`VoxelSize`], and carry [`VoxelInstance`] so the regular cleanup flow
+//! removes them.
+//!
+//! Meshing runs on the `AsyncComputeTaskPool`. [`render_voxel_chunks`] queues
+//! one job per scene and [`apply_chunk_meshes`] swaps finished results in, so
+//! old chunks stay visible until their replacements are ready. Queuing a job
+//! for a scene that already has one in flight cancels the stale job and
+//! folds its chunks into the new one.
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+use std::collections::{HashMap, HashSet};
+
+use super::dummy_renderer::{prepare_scene_root, SceneRootQuery, VoxelInstance, VoxelSize};
+use super::greedy::{chunk_coord, ChunkedVoxels};
+use super::material_registry::{VoxelMaterialRegistry, VoxelPass};
+use super::mesh_data::VoxelMeshData;
+use super::{Voxel, VoxelMeshMode, VoxelScene, VoxelsChanged};
+
+/// A greedy-meshed chunk of a voxel scene
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxelChunk {
+    /// Chunk coordinate (voxel position / `CHUNK_SIZE`)
+    pub coord: IVec3,
+    /// Number of voxels in the chunk
+    pub voxel_count: usize,
+    /// LOD level the chunk was meshed at (0 = full detail)
+    pub lod: u8,
+    /// Render pass of this chunk mesh (one entity per pass present in the chunk)
+    pub pass: VoxelPass,
+}
+
+/// Which part of a scene a mesh job rebuilds
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MeshScope {
+    /// Every chunk (or the smooth surface)
+    Full,
+    /// Only these chunk coordinates
+    Chunks(HashSet<IVec3>),
+}
+
+impl MeshScope {
+    /// Combine with the scope of a cancelled job
+    fn merge(self, other: MeshScope) -> MeshScope {
+        match (self, other) {
+            (MeshScope::Chunks(mut a), MeshScope::Chunks(b)) => {
+                a.extend(b);
+                MeshScope::Chunks(a)
+            }
+            _ => MeshScope::Full,
+        }
+    }
+}
+
+/// Meshes produced by a finished job
+enum MeshOutput {
+    /// Greedy-meshed chunk passes (empty meshes included so stale entities are removed)
+    Chunks(Vec<(VoxelChunk, VoxelMeshData)>),
+    /// Surface nets mesh for the whole scene
+    Smooth(VoxelMeshData),
+}
+
+/// An in-flight mesh job for one scene
+struct ChunkMeshJob {
+    scope: MeshScope,
+    grid: Transform,
+    voxel_count: usize,
+    task: Task<MeshOutput>,
+}
+
+/// Mesh jobs in flight, one per scene entity
+#[derive(Resource, Default)]
+pub struct ChunkMeshJobs {
+    jobs: HashMap<Entity, ChunkMeshJob>,
+}
+
+impl ChunkMeshJobs {
+    /// Number of scenes with meshing in progress
+    pub fn pending(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Whether a scene is being meshed
+    pub fn is_pending(&self, entity: Entity) -> bool {
+        self.jobs.contains_key(&entity)
+    }
+}
+
+/// Filter for scene entities that need a full rebuild
+type SceneInputsChanged = (
+    With<Handle<VoxelScene>>,
+    Or<(Changed<Handle<VoxelScene>>, Changed<VoxelMeshMode>, Changed<VoxelSize>)>,
+);
+
+/// Queue mesh jobs for changed voxel scenes
+pub fn render_voxel_chunks(
+    mut commands: Commands,
+    mut jobs: ResMut<ChunkMeshJobs>,
+    // Query for new entities, changed handles, mesh modes or voxel sizes
+    changed_scenes: Query<Entity, SceneInputsChanged>,
+    // Query for all scenes to check against asset events
+    all_scenes: Query<(Entity, &Handle<VoxelScene>, Option<&VoxelMeshMode>)>,
+    roots: SceneRootQuery,
+    scenes: Res<Assets<VoxelScene>>,
+    mut asset_events: EventReader<AssetEvent<VoxelScene>>,
+    mut voxel_edits: EventReader<VoxelsChanged>,
+    edit_tracker: Res<super::VoxelEditTracker>,
+    mut lod_state: ResMut<super::lod::VoxelLodState>,
+    registry: Res<VoxelMaterialRegistry>,
+    coloring: Res<crate::debug::DebugColoring>,
+) {
+    let mut entities_to_update: HashSet<Entity> = changed_scenes.iter().collect();
+
+    // Material passes or debug colors changed: every scene is remeshed
+    if registry.is_changed() || coloring.is_changed() {
+        entities_to_update.extend(all_scenes.iter().map(|(entity, _, _)| entity));
+    }
+    // Chunks whose LOD level changed are remeshed like edited ones
+    let mut dirty_chunks: HashMap<Entity, HashSet<IVec3>> = lod_state.take_dirty();
+
+    // 1. Handle loaded and modified assets (edits are covered by VoxelsChanged below)
+    for event in asset_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event else { continue };
+        if matches!(event, AssetEvent::Modified { .. }) && edit_tracker.is_edit(*id) {
+            continue;
+        }
+        for (entity, handle, _) in &all_scenes {
+            if handle.id() == *id {
+                entities_to_update.insert(entity);
+            }
+        }
+    }
+
+    // 2. Mark chunks touched by edits, including neighbors whose border faces may change
+    for change in voxel_edits.read() {
+        let min = chunk_coord(change.min.map(|c| c.saturating_sub(1)));
+        let max = chunk_coord(change.max.map(|c| c.saturating_add(1)));
+
+        for (entity, handle, mode) in &all_scenes {
+            if handle.id() != change.scene {
+                continue;
+            }
+            if mode.copied().unwrap_or_default() == VoxelMeshMode::Smooth {
+                entities_to_update.insert(entity);
+                continue;
+            }
+            let dirty = dirty_chunks.entry(entity).or_default();
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    for z in min.z..=max.z {
+                        dirty.insert(IVec3::new(x, y, z));
+                    }
+                }
+            }
+        }
+    }
+    dirty_chunks.retain(|entity, _| !entities_to_update.contains(entity));
+
+    // 3. Queue jobs, replacing stale ones
+    for (entity, handle, mode) in &all_scenes {
+        let mut scope = if entities_to_update.contains(&entity) {
+            MeshScope::Full
+        } else if let Some(dirty) = dirty_chunks.remove(&entity) {
+            MeshScope::Chunks(dirty)
+        } else {
+            continue;
+        };
+        let Some(scene) = scenes.get(handle) else { continue };
+
+        // Dropping the previous task cancels it; its chunks still need rebuilding
+        if let Some(stale) = jobs.jobs.remove(&entity) {
+            scope = scope.merge(stale.scope);
+        }
+
+        let smooth = mode.copied().unwrap_or_default() == VoxelMeshMode::Smooth;
+        if smooth {
+            scope = MeshScope::Full;
+        }
+
+        let grid = prepare_scene_root(&mut commands, &roots, entity, scene);
+
+        // Jobs share the scene's voxels and copy what they need on the task pool
+        let voxels = scene.shared_voxels();
+        let coloring = *coloring;
+        let task = if smooth {
+            // The surface is rebuilt whole, so the job needs every voxel but not the metadata
+            AsyncComputeTaskPool::get().spawn(async move {
+                let scene = VoxelScene::from_voxels(default(), voxels.to_vec());
+                MeshOutput::Smooth(coloring.smooth_mesh(&scene))
+            })
+        } else {
+            let chunks = match &scope {
+                MeshScope::Full => None,
+                MeshScope::Chunks(chunks) => Some(chunks.clone()),
+            };
+            let levels = lod_state.scene_levels(entity);
+            let registry = registry.clone();
+            AsyncComputeTaskPool::get().spawn(async move {
+                let voxels = match &chunks {
+                    Some(chunks) => chunk_voxels(&voxels, chunks),
+                    None => voxels.to_vec(),
+                };
+                mesh_chunks(voxels, chunks, &levels, &registry, coloring)
+            })
+        };
+
+        jobs.jobs.insert(entity, ChunkMeshJob { scope, grid, voxel_count: scene.voxel_count(), task });
+    }
+}
+
+/// Voxels in `chunks` plus a one-voxel border, enough to cull faces on chunk edges
+fn chunk_voxels(voxels: &[Voxel], chunks: &HashSet<IVec3>) -> Vec<Voxel> {
+    voxels
+        .iter()
+        .filter(|voxel| {
+            let min = chunk_coord(voxel.position.map(|c| c.saturating_sub(1)));
+            let max = chunk_coord(voxel.position.map(|c| c.saturating_add(1)));
+            (min.x..=max.x).any(|x| {
+                (min.y..=max.y).any(|y| (min.z..=max.z).any(|z| chunks.contains(&IVec3::new(x, y, z))))
+            })
+        })
+        .copied()
+        .collect()
+}
+
+/// Greedy-mesh a full scene or a set of chunks at their LOD levels, one mesh per pass
+fn mesh_chunks(
+    mut voxels: Vec<Voxel>,
+    chunks: Option<HashSet<IVec3>>,
+    levels: &HashMap<IVec3, u8>,
+    registry: &VoxelMaterialRegistry,
+    coloring: crate::debug::DebugColoring,
+) -> MeshOutput {
+    // Passes come from the original colors; debug colors keep alpha so they agree
+    coloring.recolor_voxels(&mut voxels);
+    let chunked = ChunkedVoxels::with_passes(&voxels, |v| registry.pass(v));
+    let coords: Vec<IVec3> = match chunks {
+        Some(chunks) => chunks.into_iter().collect(),
+        None => chunked.chunks.keys().copied().collect(),
+    };
+
+    let mut output = Vec::new();
+    for coord in coords {
+        let lod = levels.get(&coord).copied().unwrap_or(0);
+        for pass in chunked.chunk_passes(coord) {
+            let voxel_count = chunked.pass_voxels(coord, pass).len();
+            let mut mesh = super::lod::mesh_chunk_lod(&chunked, coord, lod, pass);
+            coloring.recolor_mesh(&mut mesh);
+            output.push((VoxelChunk { coord, voxel_count, lod, pass }, mesh));
+        }
+    }
+    MeshOutput::Chunks(output)
+}
+
+/// Swap finished mesh jobs into the world
+///
+/// Old chunk entities are despawned in the same command flush that spawns
+/// their replacements, so a scene never shows a partial rebuild.
+pub fn apply_chunk_meshes(
+    mut commands: Commands,
+    mut jobs: ResMut<ChunkMeshJobs>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut material_cache: ResMut<super::VoxelMaterialCache>,
+    scene_entities: Query<(), With<Handle<VoxelScene>>>,
+    // Query to find existing instances to despawn
+    instances: Query<(Entity, &VoxelInstance, Option<&VoxelChunk>)>,
+) {
+    let finished: Vec<Entity> = jobs
+        .jobs
+        .iter()
+        .filter(|(_, job)| job.task.is_finished())
+        .map(|(entity, _)| *entity)
+        .collect();
+    if finished.is_empty() {
+        return;
+    }
+
+    for entity in finished {
+        let Some(mut job) = jobs.jobs.remove(&entity) else { continue };
+        let Some(output) = block_on(poll_once(&mut job.task)) else { continue };
+        // Scene was despawned while meshing
+        if scene_entities.get(entity).is_err() {
+            continue;
+        }
+
+        // Despawn the entities this job replaces
+        for (instance_entity, instance, chunk) in &instances {
+            if instance.parent != entity {
+                continue;
+            }
+            let replaced = match &job.scope {
+                MeshScope::Full => true,
+                MeshScope::Chunks(coords) => chunk.is_some_and(|c| coords.contains(&c.coord)),
+            };
+            if replaced {
+                commands.entity(instance_entity).despawn_recursive();
+            }
+        }
+
+        // White base color: chunk meshes carry vertex colors (and alpha)
+        let mut spawn = |commands: &mut Commands, mesh: Handle<Mesh>, chunk: Option<VoxelChunk>| {
+            let pass = chunk.map_or(VoxelPass::Opaque, |c| c.pass);
+            let material = material_cache.pass_material(&mut materials, [255; 4], 0, pass);
+            commands.entity(entity).with_children(|parent| {
+                let mut child = parent.spawn((
+                    PbrBundle {
+                        mesh,
+                        material,
+                        transform: job.grid,
+                        ..default()
+                    },
+                    VoxelInstance { parent: entity },
+                ));
+                if let Some(chunk) = chunk {
+                    child.insert(chunk);
+                }
+            });
+        };
+
+        match output {
+            MeshOutput::Smooth(surface) => {
+                if !surface.is_empty() {
+                    spawn(&mut commands, meshes.add(surface.into_mesh()), None);
+                }
+            }
+            MeshOutput::Chunks(chunks) => {
+                let mut triangles = 0;
+                let mut spawned = HashSet::new();
+                for (chunk, chunk_mesh) in chunks {
+                    if chunk_mesh.is_empty() {
+                        continue;
+                    }
+                    triangles += chunk_mesh.triangle_count();
+                    spawned.insert(chunk.coord);
+                    spawn(&mut commands, meshes.add(chunk_mesh.into_mesh()), Some(chunk));
+                }
+
+                if job.scope == MeshScope::Full {
+                    info!("Meshed {} voxels into {} chunks ({} triangles)", job.voxel_count, spawned.len(), triangles);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::{VoxelEditCommand, VoxelEditTracker};
+
+    fn chunk_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(bevy::asset::AssetPlugin::default())
+            .init_asset::<VoxelScene>()
+            .init_asset::<Mesh>()
+            .init_asset::<StandardMaterial>()
+            .init_resource::<crate::metrics::PerformanceMetrics>()
+            .init_resource::<VoxelEditTracker>()
+            .init_resource::<crate::voxel::VoxelMaterialCache>()
+            .init_resource::<ChunkMeshJobs>()
+            .init_resource::<crate::voxel::lod::VoxelLodState>()
+            .init_resource::<VoxelMaterialRegistry>()
+            .init_resource::<crate::debug::DebugColoring>()
+            .init_resource::<crate::config::HeartOnConfig>()
+            .add_event::<VoxelEditCommand>()
+            .add_event::<VoxelsChanged>()
+            .add_systems(
+                Update,
+                (
+                    crate::voxel::edit::apply_voxel_edits,
+                    crate::voxel::lod::update_chunk_lods,
+                    render_voxel_chunks,
+                    apply_chunk_meshes,
+                )
+                    .chain(),
+            );
+        AsyncComputeTaskPool::get_or_init(bevy::tasks::TaskPool::default);
+        app
+    }
+
+    /// Update until every queued mesh job has been applied
+    fn update_until_meshed(app: &mut App) {
+        app.update();
+        for _ in 0..1000 {
+            if app.world.resource::<ChunkMeshJobs>().pending() == 0 {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            app.update();
+        }
+        panic!("mesh jobs did not finish");
+    }
+
+    fn chunk_coords(app: &mut App) -> Vec<IVec3> {
+        let mut coords: Vec<IVec3> = app
+            .world
+            .query::<&VoxelChunk>()
+            .iter(&app.world)
+            .map(|c| c.coord)
+            .collect();
+        coords.sort_by_key(|c| (c.x, c.y, c.z));
+        coords
+    }
+
+    #[test]
+    fn test_one_entity_per_chunk() {
+        let mut app = chunk_app();
+        // 40³ spans 2 chunks per axis
+        let handle = app.world.resource_mut::<Assets<VoxelScene>>().add(VoxelScene::test_cube(40));
+        app.world.spawn(handle);
+        update_until_meshed(&mut app);
+
+        assert_eq!(chunk_coords(&mut app).len(), 8);
+        assert_eq!(app.world.resource::<Assets<StandardMaterial>>().len(), 1);
+    }
+
+    #[test]
+    fn test_debug_coloring_remeshes() {
+        let mut app = chunk_app();
+        let handle = app.world.resource_mut::<Assets<VoxelScene>>().add(VoxelScene::test_cube(4));
+        app.world.spawn(handle);
+        update_until_meshed(&mut app);
+
+        *app.world.resource_mut::<crate::debug::DebugColoring>() = crate::debug::DebugColoring::Normals;
+        update_until_meshed(&mut app);
+
+        let mesh_handle = app.world.query_filtered::<&Handle<Mesh>, With<VoxelChunk>>().single(&app.world).clone();
+        let mesh = app.world.resource::<Assets<Mesh>>().get(&mesh_handle).unwrap();
+        let Some(bevy::render::mesh::VertexAttributeValues::Float32x4(colors)) = mesh.attribute(Mesh::ATTRIBUTE_COLOR) else {
+            panic!("chunk mesh has no colors");
+        };
+        // Bottom faces point down: normal (0, -1, 0) maps to green 0
+        assert!(colors.contains(&[0.5, 0.0, 0.5, 1.0]));
+    }
+
+    #[test]
+    fn test_edit_rebuilds_only_touched_chunks() {
+        let mut app = chunk_app();
+        let handle = app.world.resource_mut::<Assets<VoxelScene>>().add(VoxelScene::test_cube(40));
+        let scene = app.world.spawn(handle).id();
+        update_until_meshed(&mut app);
+
+        let before: Vec<Entity> = app.world.query_filtered::<Entity, With<VoxelChunk>>().iter(&app.world).collect();
+
+        app.world.send_event(VoxelEditCommand::Set {
+            scene,
+            voxel: Voxel { position: [39, 39, 39], color: [0, 0, 0, 255], material_id: 0 },
+        });
+        update_until_meshed(&mut app);
+
+        let after: Vec<Entity> = app.world.query_filtered::<Entity, With<VoxelChunk>>().iter(&app.world).collect();
+        assert_eq!(after.len(), 8);
+        // Only the far corner chunk was respawned
+        assert_eq!(after.iter().filter(|e| !before.contains(e)).count(), 1);
+    }
+
+    #[test]
+    fn test_requeued_job_keeps_stale_chunks() {
+        let mut app = chunk_app();
+        let handle = app.world.resource_mut::<Assets<VoxelScene>>().add(VoxelScene::test_cube(40));
+        let scene = app.world.spawn(handle).id();
+        update_until_meshed(&mut app);
+
+        let before: Vec<Entity> = app.world.query_filtered::<Entity, With<VoxelChunk>>().iter(&app.world).collect();
+
+        // Second edit arrives while the first job may still be running
+        for position in [[0, 0, 0], [39, 39, 39]] {
+            app.world.send_event(VoxelEditCommand::Set {
+                scene,
+                voxel: Voxel { position, color: [0, 0, 0, 255], material_id: 0 },
+            });
+            app.update();
+        }
+        update_until_meshed(&mut app);
+
+        let after: Vec<Entity> = app.world.query_filtered::<Entity, With<VoxelChunk>>().iter(&app.world).collect();
+        assert_eq!(after.len(), 8);
+        assert_eq!(after.iter().filter(|e| !before.contains(e)).count(), 2);
+    }
+
+    #[test]
+    fn test_distant_chunks_use_coarser_lod() {
+        let mut app = chunk_app();
+        let handle = app.world.resource_mut::<Assets<VoxelScene>>().add(VoxelScene::test_cube(40));
+        app.world.spawn(handle);
+        app.world.spawn((Camera::default(), GlobalTransform::from_xyz(0.0, 0.0, 1000.0)));
+        update_until_meshed(&mut app);
+
+        // First LOD pass sees the full-detail chunks and requests remeshing
+        update_until_meshed(&mut app);
+        app.update();
+
+        let lods: Vec<u8> = app.world.query::<&VoxelChunk>().iter(&app.world).map(|c| c.lod).collect();
+        assert_eq!(lods, [crate::voxel::lod::MAX_LOD; 8]);
+        let metrics = app.world.resource::<crate::metrics::PerformanceMetrics>();
+        assert_eq!(metrics.lod_distribution, [0, 0, 8]);
+    }
+
+    #[test]
+    fn test_transparent_voxels_get_blended_mesh() {
+        let mut app = chunk_app();
+        let mut scene = VoxelScene::test_cube(2);
+        scene
+            .add_voxel(Voxel { position: [0, 2, 0], color: [0, 0, 255, 100], material_id: 0 })
+            .unwrap();
+        let handle = app.world.resource_mut::<Assets<VoxelScene>>().add(scene);
+        app.world.spawn(handle);
+        update_until_meshed(&mut app);
+
+        let mut chunks: Vec<(VoxelChunk, Handle<StandardMaterial>)> = app
+            .world
+            .query::<(&VoxelChunk, &Handle<StandardMaterial>)>()
+            .iter(&app.world)
+            .map(|(c, m)| (*c, m.clone()))
+            .collect();
+        chunks.sort_by_key(|(c, _)| c.pass);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!((chunks[0].0.pass, chunks[0].0.voxel_count), (VoxelPass::Opaque, 8));
+        assert_eq!((chunks[1].0.pass, chunks[1].0.voxel_count), (VoxelPass::Blend, 1));
+        let materials = app.world.resource::<Assets<StandardMaterial>>();
+        assert_eq!(materials.get(&chunks[1].1).unwrap().alpha_mode, AlphaMode::Blend);
+    }
+
+    #[test]
+    fn test_chunk_jobs_copy_only_dirty_chunks_and_border() {
+        let scene = VoxelScene::test_cube(40);
+        let voxels = chunk_voxels(scene.voxels(), &[IVec3::ZERO].into());
+        // The 32³ chunk plus the first layer of its +X, +Y and +Z neighbors
+        assert_eq!(voxels.len(), 33 * 33 * 33);
+        assert!(voxels.iter().all(|v| v.position.iter().all(|&c| c <= 32)));
+    }
+
+    #[test]
+    fn test_scope_merge() {
+        let a = MeshScope::Chunks([IVec3::ZERO].into());
+        let b = MeshScope::Chunks([IVec3::ONE].into());
+        assert_eq!(a.clone().merge(b), MeshScope::Chunks([IVec3::ZERO, IVec3::ONE].into()));
+        assert_eq!(a.merge(MeshScope::Full), MeshScope::Full);
+    }
+
+    #[test]
+    fn test_chunks_follow_origin_and_voxel_size() {
+        let mut app = chunk_app();
+        let mut scene = VoxelScene::test_cube(2);
+        scene.metadata.origin = Vec3::new(10.0, 0.0, -4.0);
+        let handle = app.world.resource_mut::<Assets<VoxelScene>>().add(scene);
+        let root = app.world.spawn((handle, VoxelSize(0.25))).id();
+        update_until_meshed(&mut app);
+
+        let mut chunks = app.world.query_filtered::<(&Parent, &Transform), With<VoxelChunk>>();
+        let (parent, transform) = chunks.single(&app.world);
+        assert_eq!(parent.get(), root);
+        assert_eq!(transform.translation, Vec3::new(10.0, 0.0, -4.0));
+        assert_eq!(transform.scale, Vec3::splat(0.25));
+        assert!(app.world.get::<GlobalTransform>(root).is_some());
+    }
+}
@@ -0,0 +1,195 @@
+// SPDX-License-Identifier: MIT
+//! CPU frustum culling of voxel chunks
+//!
+//! World-space chunk bounds are kept in SoA form ([`SimdAabbX4`]) and tested
+//! against every active camera frustum four chunks at a time. Chunks outside
+//! all frusta are hidden through `Visibility`; the visible and culled voxel
+//! totals are written to `PerformanceMetrics`.
+
+use bevy::prelude::*;
+use bevy::render::primitives::{Aabb, Frustum};
+
+use super::greedy::CHUNK_SIZE;
+use super::VoxelChunk;
+use crate::simd::soa::SimdAabbX4;
+
+/// Chunk bounds in SoA form, rebuilt when chunks move, spawn or despawn
+#[derive(Resource, Default)]
+pub struct VoxelChunkBounds {
+    entities: Vec<Entity>,
+    voxel_counts: Vec<usize>,
+    lanes: Vec<SimdAabbX4>,
+}
+
+impl VoxelChunkBounds {
+    /// Number of chunks tracked
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Whether no chunks are tracked
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Rebuild from chunk entities and their world-space bounds
+    fn rebuild(&mut self, chunks: impl Iterator<Item = (Entity, usize, Aabb)>) {
+        self.entities.clear();
+        self.voxel_counts.clear();
+        let mut aabbs = Vec::new();
+        for (entity, voxel_count, aabb) in chunks {
+            self.entities.push(entity);
+            self.voxel_counts.push(voxel_count);
+            aabbs.push(aabb);
+        }
+
+        // Pad the last group by repeating its final AABB; padded lanes are masked out
+        self.lanes = aabbs
+            .chunks(4)
+            .map(|group| {
+                let lane = |i: usize| &group[i.min(group.len() - 1)];
+                SimdAabbX4::from_aabbs(lane(0), lane(1), lane(2), lane(3))
+            })
+            .collect();
+    }
+
+    /// Visibility of each tracked chunk, in tracking order
+    fn visible(&self, frusta: &[Frustum]) -> Vec<bool> {
+        let mut visible = Vec::with_capacity(self.entities.len());
+        for (group, lane) in self.lanes.iter().enumerate() {
+            let mask = frusta
+                .iter()
+                .fold(0u8, |mask, frustum| mask | lane.intersects_frustum(frustum));
+            let count = (self.entities.len() - group * 4).min(4);
+            visible.extend((0..count).map(|i| mask & (1 << i) != 0));
+        }
+        visible
+    }
+}
+
+/// World-space bounds of a chunk entity
+pub fn chunk_world_aabb(coord: IVec3, transform: &GlobalTransform) -> Aabb {
+    let size = CHUNK_SIZE as f32;
+    // Voxels are centered on their grid position
+    let min = coord.as_vec3() * size - Vec3::splat(0.5);
+    let max = min + Vec3::splat(size);
+
+    let affine = transform.affine();
+    let mut world_min = Vec3::splat(f32::MAX);
+    let mut world_max = Vec3::splat(f32::MIN);
+    for i in 0..8 {
+        let corner = Vec3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        );
+        let world = affine.transform_point3(corner);
+        world_min = world_min.min(world);
+        world_max = world_max.max(world);
+    }
+    Aabb::from_min_max(world_min, world_max)
+}
+
+/// Filter for chunks whose world bounds need recomputing
+type ChunkBoundsChanged = (With<VoxelChunk>, Or<(Added<VoxelChunk>, Changed<GlobalTransform>)>);
+
+/// Hide voxel chunks outside every active camera frustum
+///
+/// Runs in `PostUpdate` after transforms and frusta are up to date and before
+/// visibility propagates.
+pub fn cull_voxel_chunks(
+    mut bounds: ResMut<VoxelChunkBounds>,
+    changed: Query<(), ChunkBoundsChanged>,
+    mut removed: RemovedComponents<VoxelChunk>,
+    mut chunks: Query<(Entity, &VoxelChunk, &GlobalTransform, &mut Visibility)>,
+    cameras: Query<(&Camera, &Frustum)>,
+    mut metrics: ResMut<crate::metrics::PerformanceMetrics>,
+) {
+    let removed_any = removed.read().count() > 0;
+    if removed_any || !changed.is_empty() {
+        bounds.rebuild(
+            chunks
+                .iter()
+                .map(|(entity, chunk, transform, _)| (entity, chunk.voxel_count, chunk_world_aabb(chunk.coord, transform))),
+        );
+    }
+
+    let frusta: Vec<Frustum> = cameras
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .map(|(_, frustum)| *frustum)
+        .collect();
+
+    let mut visible_voxels = 0;
+    let mut culled_voxels = 0;
+
+    // Without a camera nothing is culled
+    let visible = if frusta.is_empty() {
+        vec![true; bounds.len()]
+    } else {
+        bounds.visible(&frusta)
+    };
+
+    for ((entity, voxel_count), visible) in bounds.entities.iter().zip(&bounds.voxel_counts).zip(visible) {
+        let Ok((_, _, _, mut visibility)) = chunks.get_mut(*entity) else { continue };
+        let target = if visible { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != target {
+            *visibility = target;
+        }
+
+        if visible {
+            visible_voxels += voxel_count;
+        } else {
+            culled_voxels += voxel_count;
+        }
+    }
+
+    metrics.visible_voxel_count = visible_voxels;
+    metrics.culled_voxel_count = culled_voxels;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera_frustum(eye: Vec3, target: Vec3) -> Frustum {
+        let view_projection =
+            Mat4::perspective_rh(1.0, 1.0, 0.1, 500.0) * Mat4::look_at_rh(eye, target, Vec3::Y);
+        Frustum::from_view_projection(&view_projection)
+    }
+
+    fn chunk_bounds(coords: &[IVec3]) -> VoxelChunkBounds {
+        let mut bounds = VoxelChunkBounds::default();
+        bounds.rebuild(
+            coords
+                .iter()
+                .enumerate()
+                .map(|(i, &c)| (Entity::from_raw(i as u32), 1, chunk_world_aabb(c, &GlobalTransform::IDENTITY))),
+        );
+        bounds
+    }
+
+    #[test]
+    fn test_chunks_behind_camera_are_culled() {
+        // Camera looking down -Z
+        let frustum = camera_frustum(Vec3::new(16.0, 16.0, 0.0), Vec3::new(16.0, 16.0, -100.0));
+        let bounds = chunk_bounds(&[
+            IVec3::new(0, 0, -2),
+            IVec3::new(0, 0, 2),
+            IVec3::new(0, 0, -3),
+            IVec3::new(0, 0, 3),
+            IVec3::new(0, 0, -4),
+        ]);
+
+        assert_eq!(bounds.lanes.len(), 2);
+        assert_eq!(bounds.visible(&[frustum]), [true, false, true, false, true]);
+    }
+
+    #[test]
+    fn test_chunk_aabb_follows_transform() {
+        let transform = GlobalTransform::from(Transform::from_xyz(10.0, 0.0, 0.0).with_scale(Vec3::splat(0.5)));
+        let aabb = chunk_world_aabb(IVec3::new(1, 0, 0), &transform);
+        assert_eq!(Vec3::from(aabb.min()), Vec3::new(10.0 + 31.5 * 0.5, -0.25, -0.25));
+        assert_eq!(Vec3::from(aabb.max()), Vec3::new(10.0 + 63.5 * 0.5, 15.75, 15.75));
+    }
+}
@@ -10,35 +10,111 @@ pub struct VoxelInstance {
     pub parent: Entity,
 }
 
+/// Grid position of a per-voxel cube instance
+#[derive(Component, Debug, Clone, Copy)]
+pub struct VoxelCell {
+    /// Voxel grid position
+    pub position: [u16; 3],
+}
+
 /// Marker component for voxel scene root
 #[derive(Component)]
 pub struct VoxelSceneRoot;
 
+/// World-space edge length of one voxel for a scene entity (overrides the scene's `voxel_size`)
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct VoxelSize(pub f32);
+
+impl Default for VoxelSize {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+impl VoxelSize {
+    /// Transform from scene grid space into the scene root's local space
+    ///
+    /// Grid positions are offset by the scene origin and scaled by the voxel size.
+    pub fn grid_transform(self, origin: Vec3) -> Transform {
+        Transform::from_translation(origin).with_scale(Vec3::splat(self.0))
+    }
+}
+
+/// Per-scene data needed to parent render entities under a scene root
+pub(crate) type SceneRootQuery<'w, 's> =
+    Query<'w, 's, (Option<&'static VoxelSize>, Has<Transform>, Has<Visibility>)>;
+
+/// Mark an entity as a voxel scene root, adding any spatial components it lacks
+///
+/// Returns the grid transform for render entities spawned under it.
+pub(crate) fn prepare_scene_root(
+    commands: &mut Commands,
+    roots: &SceneRootQuery,
+    entity: Entity,
+    scene: &crate::voxel::VoxelScene,
+) -> Transform {
+    let (size, has_transform, has_visibility) = roots.get(entity).unwrap_or((None, true, true));
+
+    let mut root = commands.entity(entity);
+    root.insert(VoxelSceneRoot);
+    if !has_transform {
+        root.insert(TransformBundle::default());
+    }
+    if !has_visibility {
+        root.insert(VisibilityBundle::default());
+    }
+
+    scene.grid_transform(size)
+}
+
 /// Render voxel scenes as instanced cubes (Community Edition renderer)
+///
+/// Every scene is drawn as cubes; [`crate::voxel::VoxelMeshMode::Smooth`] is
+/// left to the chunked backend, which builds smooth meshes off the main thread.
 pub fn render_dummy_voxels(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut material_cache: ResMut<crate::voxel::VoxelMaterialCache>,
     // Query for new entities or entities with changed handles
     changed_scenes: Query<(Entity, &Handle<crate::voxel::VoxelScene>), Changed<Handle<crate::voxel::VoxelScene>>>,
+    // Query for scenes whose voxel size was changed
+    changed_sizes: Query<Entity, (Changed<VoxelSize>, With<Handle<crate::voxel::VoxelScene>>)>,
     // Query for all scenes to check against asset events
     all_scenes: Query<(Entity, &Handle<crate::voxel::VoxelScene>)>,
+    roots: SceneRootQuery,
     scenes: Res<Assets<crate::voxel::VoxelScene>>,
     mut asset_events: EventReader<AssetEvent<crate::voxel::VoxelScene>>,
+    // Edit commands are applied incrementally instead of respawning the scene
+    mut voxel_edits: EventReader<crate::voxel::VoxelsChanged>,
+    edit_tracker: Res<crate::voxel::VoxelEditTracker>,
+    registry: Res<crate::voxel::VoxelMaterialRegistry>,
+    coloring: Res<crate::debug::DebugColoring>,
     // Query to find existing instances to despawn
-    instances: Query<(Entity, &VoxelInstance)>,
-    mut metrics: ResMut<crate::metrics::PerformanceMetrics>,
+    instances: Query<(Entity, &VoxelInstance, Option<&VoxelCell>)>,
 ) {
     let mut entities_to_update = std::collections::HashSet::new();
+    let mut edited: std::collections::HashMap<Entity, Vec<crate::voxel::VoxelsChanged>> = default();
+
+    // Material passes or debug colors changed: every cube may need a different material
+    if registry.is_changed() || coloring.is_changed() {
+        entities_to_update.extend(all_scenes.iter().map(|(entity, _)| entity));
+    }
 
     // 1. Handle new/changed components
     for (entity, _) in &changed_scenes {
         entities_to_update.insert(entity);
     }
+    for entity in &changed_sizes {
+        entities_to_update.insert(entity);
+    }
 
-    // 2. Handle modified assets
+    // 2. Handle modified assets (edits are covered by VoxelsChanged below)
     for event in asset_events.read() {
         if let AssetEvent::Modified { id } = event {
+            if edit_tracker.is_edit(*id) {
+                continue;
+            }
             for (entity, handle) in &all_scenes {
                 if handle.id() == *id {
                     entities_to_update.insert(entity);
@@ -47,24 +123,54 @@ pub fn render_dummy_voxels(
         }
     }
 
-    if entities_to_update.is_empty() {
+    // 3. Collect edited regions
+    for change in voxel_edits.read() {
+        for (entity, handle) in &all_scenes {
+            if handle.id() == change.scene {
+                edited.entry(entity).or_default().push(*change);
+            }
+        }
+    }
+    edited.retain(|entity, _| !entities_to_update.contains(entity));
+
+    if entities_to_update.is_empty() && edited.is_empty() {
         return;
     }
 
-    // 3. Despawn old instances for updated entities
-    for (instance_entity, instance) in &instances {
+    // 4. Respawn cubes inside edited regions only
+    for (entity, changes) in edited {
+        let Ok((_, handle)) = all_scenes.get(entity) else { continue };
+        let Some(scene) = scenes.get(handle) else { continue };
+        let in_changes = |position: [u16; 3]| changes.iter().any(|c| c.contains(position));
+
+        for (instance_entity, instance, cell) in &instances {
+            if instance.parent == entity && cell.is_some_and(|c| in_changes(c.position)) {
+                commands.entity(instance_entity).despawn_recursive();
+            }
+        }
+
+        let grid = prepare_scene_root(&mut commands, &roots, entity, scene);
+        let cube_mesh = material_cache.cube_mesh(&mut meshes);
+        for voxel in scene.voxels().iter().filter(|v| in_changes(v.position)) {
+            let material = material_cache.pass_material(&mut materials, coloring.voxel_color(voxel), voxel.material_id, registry.pass(voxel));
+            spawn_voxel_cube(&mut commands, &cube_mesh, material, entity, grid, voxel);
+        }
+    }
+
+    // 5. Despawn old instances for updated entities
+    for (instance_entity, instance, _) in &instances {
         if entities_to_update.contains(&instance.parent) {
-            commands.entity(instance_entity).despawn();
+            commands.entity(instance_entity).despawn_recursive();
         }
     }
 
-    // 4. Spawn new instances
+    // 6. Spawn new instances
     for entity in entities_to_update {
         let Ok((_, handle)) = all_scenes.get(entity) else { continue };
         let Some(scene) = scenes.get(handle) else { continue };
         
         let voxel_count = scene.voxel_count();
-        
+
         // Performance warning for large scenes
         if voxel_count > 100_000 {
             warn!(
@@ -76,14 +182,11 @@ pub fn render_dummy_voxels(
         
         info!("Rendering {} voxels as instanced cubes", voxel_count);
         
-        // Update metrics
-        metrics.voxel_count = voxel_count;
-        
         // Mark entity as voxel scene root
-        commands.entity(entity).insert(VoxelSceneRoot);
+        let grid = prepare_scene_root(&mut commands, &roots, entity, scene);
         
-        // Create shared cube mesh
-        let cube_mesh = meshes.add(Cuboid::new(1.0, 1.0, 1.0));
+        // Shared cube mesh
+        let cube_mesh = material_cache.cube_mesh(&mut meshes);
         
         // Get voxels from scene
         let voxels = match &scene.voxel_data {
@@ -95,38 +198,44 @@ pub fn render_dummy_voxels(
         };
         
         // Spawn a cube for each voxel (instanced rendering)
-        for voxel in voxels {
-            let color = Color::rgba_u8(
-                voxel.color[0],
-                voxel.color[1],
-                voxel.color[2],
-                voxel.color[3],
-            );
-            
-            commands.spawn((
-                PbrBundle {
-                    mesh: cube_mesh.clone(),
-                    material: materials.add(StandardMaterial {
-                        base_color: color,
-                        perceptual_roughness: 0.8,
-                        metallic: 0.0,
-                        ..default()
-                    }),
-                    transform: Transform::from_translation(Vec3::new(
-                        voxel.position[0] as f32,
-                        voxel.position[1] as f32,
-                        voxel.position[2] as f32,
-                    )),
-                    ..default()
-                },
-                VoxelInstance { parent: entity },
-            ));
+        for voxel in voxels.iter() {
+            let material = material_cache.pass_material(&mut materials, coloring.voxel_color(voxel), voxel.material_id, registry.pass(voxel));
+            spawn_voxel_cube(&mut commands, &cube_mesh, material, entity, grid, voxel);
         }
         
         info!("Spawned {} voxel instances", voxels.len());
     }
 }
 
+/// Spawn one cube entity for a voxel as a child of its scene root
+fn spawn_voxel_cube(
+    commands: &mut Commands,
+    cube_mesh: &Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+    parent: Entity,
+    grid: Transform,
+    voxel: &crate::voxel::Voxel,
+) {
+    let local = Transform::from_translation(Vec3::new(
+        voxel.position[0] as f32,
+        voxel.position[1] as f32,
+        voxel.position[2] as f32,
+    ));
+    let cube = commands
+        .spawn((
+            PbrBundle {
+                mesh: cube_mesh.clone(),
+                material,
+                transform: grid.mul_transform(local),
+                ..default()
+            },
+            VoxelInstance { parent },
+            VoxelCell { position: voxel.position },
+        ))
+        .id();
+    commands.entity(parent).add_child(cube);
+}
+
 /// Clean up voxel instances when scene is removed
 pub fn cleanup_voxel_instances(
     mut commands: Commands,
@@ -137,7 +246,7 @@ pub fn cleanup_voxel_instances(
         // Remove all instances for this scene
         for (instance_entity, instance) in &instance_query {
             if instance.parent == removed_entity {
-                commands.entity(instance_entity).despawn();
+                commands.entity(instance_entity).despawn_recursive();
             }
         }
     }
@@ -154,4 +263,34 @@ mod tests {
         
         assert_eq!(instance.parent, parent);
     }
+
+    #[test]
+    fn test_materials_shared_across_scenes() {
+        let mut app = App::new();
+        app.add_plugins(bevy::asset::AssetPlugin::default())
+            .init_asset::<crate::voxel::VoxelScene>()
+            .init_asset::<Mesh>()
+            .init_asset::<StandardMaterial>()
+            .init_resource::<crate::metrics::PerformanceMetrics>()
+            .init_resource::<crate::voxel::VoxelEditTracker>()
+            .init_resource::<crate::voxel::VoxelMaterialCache>()
+            .init_resource::<crate::voxel::VoxelMaterialRegistry>()
+            .init_resource::<crate::debug::DebugColoring>()
+            .add_event::<crate::voxel::VoxelsChanged>()
+            .add_systems(Update, render_dummy_voxels);
+
+        // Two scenes with the same 8 voxel colors
+        for _ in 0..2 {
+            let handle = app
+                .world
+                .resource_mut::<Assets<crate::voxel::VoxelScene>>()
+                .add(crate::voxel::VoxelScene::test_cube(2));
+            app.world.spawn(handle);
+        }
+        app.update();
+
+        assert_eq!(app.world.query::<&VoxelInstance>().iter(&app.world).count(), 16);
+        assert_eq!(app.world.resource::<Assets<StandardMaterial>>().len(), 8);
+        assert_eq!(app.world.resource::<Assets<Mesh>>().len(), 1);
+    }
 }
@@ -0,0 +1,243 @@
+// SPDX-License-Identifier: MIT
+//! ECS-driven voxel editing
+//!
+//! Systems send [`VoxelEditCommand`]s instead of mutating `Assets<VoxelScene>`
+//! directly. Commands are applied in [`super::VoxelSet::Edit`] and each
+//! applied command emits a [`VoxelsChanged`] event describing the affected
+//! region, so renderers and navigation can update incrementally.
+
+use bevy::prelude::*;
+use std::collections::HashSet;
+
+use super::scene::{Voxel, VoxelScene};
+
+/// Edit request targeting the entity that holds a `Handle<VoxelScene>`
+#[derive(Event, Debug, Clone, Copy)]
+pub enum VoxelEditCommand {
+    /// Add or replace a single voxel
+    Set {
+        /// Scene entity
+        scene: Entity,
+        /// Voxel to write
+        voxel: Voxel,
+    },
+    /// Remove the voxel at a position
+    Remove {
+        /// Scene entity
+        scene: Entity,
+        /// Grid position
+        position: [u16; 3],
+    },
+    /// Fill an inclusive box with one color and material
+    FillRegion {
+        /// Scene entity
+        scene: Entity,
+        /// First corner (inclusive)
+        min: [u16; 3],
+        /// Opposite corner (inclusive)
+        max: [u16; 3],
+        /// RGBA color
+        color: [u8; 4],
+        /// Material ID
+        material_id: u8,
+    },
+}
+
+impl VoxelEditCommand {
+    /// Scene entity this command targets
+    pub fn scene(&self) -> Entity {
+        match *self {
+            Self::Set { scene, .. } | Self::Remove { scene, .. } | Self::FillRegion { scene, .. } => scene,
+        }
+    }
+}
+
+/// Emitted after an edit command changed voxels in a scene
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxelsChanged {
+    /// Entity the edit command targeted
+    pub entity: Entity,
+    /// Edited scene asset (shared by every entity holding the same handle)
+    pub scene: AssetId<VoxelScene>,
+    /// Minimum corner of the affected region (inclusive)
+    pub min: [u16; 3],
+    /// Maximum corner of the affected region (inclusive)
+    pub max: [u16; 3],
+}
+
+impl VoxelsChanged {
+    /// Whether a grid position lies inside the affected region
+    pub fn contains(&self, position: [u16; 3]) -> bool {
+        (0..3).all(|i| position[i] >= self.min[i] && position[i] <= self.max[i])
+    }
+}
+
+/// Scenes modified through edit commands
+///
+/// Applying an edit still fires a coarse `AssetEvent::Modified`, delivered in
+/// the following frame. Consumers that handle [`VoxelsChanged`] use
+/// [`VoxelEditTracker::is_edit`] to skip those events. Mutating a scene
+/// directly in the same frame as an edit command is not detected.
+#[derive(Resource, Debug, Default)]
+pub struct VoxelEditTracker {
+    current: HashSet<AssetId<VoxelScene>>,
+    previous: HashSet<AssetId<VoxelScene>>,
+}
+
+impl VoxelEditTracker {
+    /// Whether a `Modified` event for this scene was caused by edit commands
+    pub fn is_edit(&self, id: AssetId<VoxelScene>) -> bool {
+        self.current.contains(&id) || self.previous.contains(&id)
+    }
+}
+
+/// Age the edit tracker once per frame
+pub fn rotate_edit_tracker(mut tracker: ResMut<VoxelEditTracker>) {
+    tracker.previous = std::mem::take(&mut tracker.current);
+}
+
+/// Apply queued edit commands to their scenes
+pub fn apply_voxel_edits(
+    mut edits: EventReader<VoxelEditCommand>,
+    mut changes: EventWriter<VoxelsChanged>,
+    mut scenes: ResMut<Assets<VoxelScene>>,
+    handles: Query<&Handle<VoxelScene>>,
+    mut tracker: ResMut<VoxelEditTracker>,
+) {
+    for edit in edits.read() {
+        let entity = edit.scene();
+        let Ok(handle) = handles.get(entity) else {
+            warn!("Voxel edit targets {:?}, which has no VoxelScene handle", entity);
+            continue;
+        };
+        let Some(scene) = scenes.get_mut(handle) else {
+            warn!("Voxel edit targets a scene that is not loaded yet");
+            continue;
+        };
+
+        let result = match *edit {
+            VoxelEditCommand::Set { voxel, .. } => scene
+                .add_voxel(voxel)
+                .map(|_| Some((voxel.position, voxel.position))),
+            VoxelEditCommand::Remove { position, .. } => scene
+                .remove_voxel(position)
+                .map(|removed| removed.then_some((position, position))),
+            VoxelEditCommand::FillRegion { min, max, color, material_id, .. } => {
+                let (min, max) = ordered_corners(min, max);
+                scene
+                    .fill_region(min, max, color, material_id)
+                    .map(|_| Some((min, max)))
+            }
+        };
+
+        match result {
+            Ok(Some((min, max))) => {
+                tracker.current.insert(handle.id());
+                changes.send(VoxelsChanged { entity, scene: handle.id(), min, max });
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Voxel edit failed: {}", e),
+        }
+    }
+}
+
+fn ordered_corners(a: [u16; 3], b: [u16; 3]) -> ([u16; 3], [u16; 3]) {
+    (
+        [a[0].min(b[0]), a[1].min(b[1]), a[2].min(b[2])],
+        [a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(bevy::asset::AssetPlugin::default())
+            .init_asset::<VoxelScene>()
+            .init_resource::<VoxelEditTracker>()
+            .add_event::<VoxelEditCommand>()
+            .add_event::<VoxelsChanged>()
+            .add_systems(Update, apply_voxel_edits);
+
+        let handle = app.world.resource_mut::<Assets<VoxelScene>>().add(VoxelScene::test_cube(2));
+        let entity = app.world.spawn(handle).id();
+        (app, entity)
+    }
+
+    fn drain_changes(app: &mut App) -> Vec<VoxelsChanged> {
+        app.world.resource_mut::<Events<VoxelsChanged>>().drain().collect()
+    }
+
+    fn scene(app: &App, entity: Entity) -> &VoxelScene {
+        let handle = app.world.get::<Handle<VoxelScene>>(entity).unwrap();
+        app.world.resource::<Assets<VoxelScene>>().get(handle).unwrap()
+    }
+
+    #[test]
+    fn test_set_and_remove_emit_changes() {
+        let (mut app, entity) = edit_app();
+
+        app.world.send_event(VoxelEditCommand::Set {
+            scene: entity,
+            voxel: Voxel { position: [5, 5, 5], color: [255, 0, 0, 255], material_id: 1 },
+        });
+        app.update();
+
+        let changes = drain_changes(&mut app);
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].min, changes[0].max), ([5, 5, 5], [5, 5, 5]));
+        assert_eq!(changes[0].entity, entity);
+        assert_eq!(scene(&app, entity).voxel_count(), 9);
+
+        app.world.send_event(VoxelEditCommand::Remove { scene: entity, position: [5, 5, 5] });
+        app.update();
+        assert_eq!(drain_changes(&mut app).len(), 1);
+        assert_eq!(scene(&app, entity).voxel_count(), 8);
+    }
+
+    #[test]
+    fn test_removing_missing_voxel_emits_nothing() {
+        let (mut app, entity) = edit_app();
+        app.world.send_event(VoxelEditCommand::Remove { scene: entity, position: [9, 9, 9] });
+        app.update();
+        assert!(drain_changes(&mut app).is_empty());
+    }
+
+    #[test]
+    fn test_fill_region_normalizes_corners() {
+        let (mut app, entity) = edit_app();
+        app.world.send_event(VoxelEditCommand::FillRegion {
+            scene: entity,
+            min: [3, 3, 3],
+            max: [2, 2, 2],
+            color: [0, 0, 255, 255],
+            material_id: 0,
+        });
+        app.update();
+
+        let changes = drain_changes(&mut app);
+        assert_eq!((changes[0].min, changes[0].max), ([2, 2, 2], [3, 3, 3]));
+        assert!(changes[0].contains([3, 2, 3]));
+        assert!(!changes[0].contains([1, 2, 3]));
+        assert_eq!(scene(&app, entity).voxel_count(), 16);
+    }
+
+    #[test]
+    fn test_tracker_marks_edited_scenes() {
+        let (mut app, entity) = edit_app();
+        let id = app.world.get::<Handle<VoxelScene>>(entity).unwrap().id();
+
+        app.world.send_event(VoxelEditCommand::Remove { scene: entity, position: [0, 0, 0] });
+        app.update();
+        assert!(app.world.resource::<VoxelEditTracker>().is_edit(id));
+
+        // Survives one rotation (the frame the Modified event is read), then expires
+        let mut tracker = app.world.resource_mut::<VoxelEditTracker>();
+        tracker.previous = std::mem::take(&mut tracker.current);
+        assert!(tracker.is_edit(id));
+        tracker.previous = std::mem::take(&mut tracker.current);
+        assert!(!tracker.is_edit(id));
+    }
+}
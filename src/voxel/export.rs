@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: MIT
+//! Triangle mesh export to glTF 2.0 (`.glb`) and Wavefront OBJ + MTL
+//!
+//! Scenes are greedy-meshed with hidden faces culled, then placed with the
+//! scene's origin and voxel size so exported meshes line up with what the
+//! engine renders. Colors are written per vertex; glTF gets linear `COLOR_0`,
+//! OBJ gets sRGB values appended to each `v` line.
+
+use bevy::prelude::*;
+
+use super::greedy::ChunkedVoxels;
+use super::mesh_data::VoxelMeshData;
+use super::scene::VoxelScene;
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_JSON: &[u8; 4] = b"JSON";
+const GLB_BIN: &[u8; 4] = b"BIN\0";
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+/// Mesh a whole scene into one triangle mesh in scene-local space
+pub fn scene_mesh(scene: &VoxelScene) -> VoxelMeshData {
+    let chunked = ChunkedVoxels::new(scene.voxels());
+    let mut coords: Vec<IVec3> = chunked.chunks.keys().copied().collect();
+    coords.sort_by_key(|c| c.to_array());
+
+    let mut mesh = VoxelMeshData::default();
+    for coord in coords {
+        let chunk = chunked.mesh_chunk(coord);
+        let base = mesh.positions.len() as u32;
+        mesh.positions.extend(chunk.positions);
+        mesh.normals.extend(chunk.normals);
+        mesh.colors.extend(chunk.colors);
+        mesh.indices.extend(chunk.indices.iter().map(|i| base + i));
+    }
+
+    let transform = scene.grid_transform(None);
+    for position in &mut mesh.positions {
+        *position = transform.transform_point(Vec3::from(*position)).to_array();
+    }
+    mesh
+}
+
+/// Encode a mesh as a binary glTF 2.0 file
+pub fn write_glb(mesh: &VoxelMeshData, name: &str) -> Result<Vec<u8>, String> {
+    if mesh.is_empty() {
+        return Err("Cannot export an empty mesh".to_string());
+    }
+
+    let mut bin = Vec::new();
+    let mut views = Vec::new();
+    let mut push_view = |bytes: Vec<u8>, target: u32| {
+        views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#,
+            bin.len(),
+            bytes.len(),
+            target
+        ));
+        bin.extend(bytes);
+    };
+    push_view(mesh.positions.iter().flatten().flat_map(|f| f.to_le_bytes()).collect(), ARRAY_BUFFER);
+    push_view(mesh.normals.iter().flatten().flat_map(|f| f.to_le_bytes()).collect(), ARRAY_BUFFER);
+    push_view(mesh.colors.iter().flatten().flat_map(|f| f.to_le_bytes()).collect(), ARRAY_BUFFER);
+    push_view(mesh.indices.iter().flat_map(|i| i.to_le_bytes()).collect(), ELEMENT_ARRAY_BUFFER);
+
+    // POSITION accessors must carry bounds
+    let (min, max) = mesh.positions.iter().fold((Vec3::MAX, Vec3::MIN), |(min, max), p| {
+        (min.min(Vec3::from(*p)), max.max(Vec3::from(*p)))
+    });
+    let vertices = mesh.vertex_count();
+    let accessors = [
+        format!(
+            r#"{{"bufferView":0,"componentType":{FLOAT},"count":{vertices},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+            min.x, min.y, min.z, max.x, max.y, max.z
+        ),
+        format!(r#"{{"bufferView":1,"componentType":{FLOAT},"count":{vertices},"type":"VEC3"}}"#),
+        format!(r#"{{"bufferView":2,"componentType":{FLOAT},"count":{vertices},"type":"VEC4"}}"#),
+        format!(r#"{{"bufferView":3,"componentType":{UNSIGNED_INT},"count":{},"type":"SCALAR"}}"#, mesh.indices.len()),
+    ];
+    let alpha_mode = if mesh.colors.iter().any(|c| c[3] < 1.0) { "BLEND" } else { "OPAQUE" };
+    let name = json_string(name);
+
+    let mut json = format!(
+        concat!(
+            r#"{{"asset":{{"version":"2.0","generator":"HeartOn"}},"scene":0,"scenes":[{{"nodes":[0]}}],"#,
+            r#""nodes":[{{"mesh":0,"name":{name}}}],"#,
+            r#""meshes":[{{"name":{name},"primitives":[{{"attributes":{{"POSITION":0,"NORMAL":1,"COLOR_0":2}},"indices":3,"material":0}}]}}],"#,
+            r#""materials":[{{"pbrMetallicRoughness":{{"baseColorFactor":[1,1,1,1],"metallicFactor":0,"roughnessFactor":1}},"alphaMode":"{alpha_mode}"}}],"#,
+            r#""accessors":[{accessors}],"bufferViews":[{views}],"buffers":[{{"byteLength":{length}}}]}}"#
+        ),
+        name = name,
+        alpha_mode = alpha_mode,
+        accessors = accessors.join(","),
+        views = views.join(","),
+        length = bin.len(),
+    )
+    .into_bytes();
+
+    // Chunks are 4-byte aligned: JSON pads with spaces, BIN with zeros
+    json.resize(json.len().next_multiple_of(4), b' ');
+    bin.resize(bin.len().next_multiple_of(4), 0);
+
+    let total = 12 + 8 + json.len() + 8 + bin.len();
+    let total = u32::try_from(total).map_err(|_| "Mesh too large for .glb".to_string())?;
+    let mut bytes = Vec::with_capacity(total as usize);
+    bytes.extend_from_slice(GLB_MAGIC);
+    bytes.extend_from_slice(&2u32.to_le_bytes());
+    bytes.extend_from_slice(&total.to_le_bytes());
+    for (kind, chunk) in [(GLB_JSON, &json), (GLB_BIN, &bin)] {
+        bytes.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(chunk);
+    }
+    Ok(bytes)
+}
+
+/// Encode a mesh as OBJ text plus the MTL it references
+///
+/// `mtl_file` is the file name written into the `mtllib` statement.
+pub fn write_obj(mesh: &VoxelMeshData, name: &str, mtl_file: &str) -> (String, String) {
+    use std::fmt::Write;
+
+    let mut obj = format!("# HeartOn voxel export\nmtllib {}\no {}\n", mtl_file, name);
+    for (position, color) in mesh.positions.iter().zip(&mesh.colors) {
+        let [r, g, b, _] = Color::rgba_linear(color[0], color[1], color[2], color[3]).as_rgba_f32();
+        let _ = writeln!(obj, "v {} {} {} {:.4} {:.4} {:.4}", position[0], position[1], position[2], r, g, b);
+    }
+    for normal in &mesh.normals {
+        let _ = writeln!(obj, "vn {} {} {}", normal[0], normal[1], normal[2]);
+    }
+    obj.push_str("usemtl voxel\n");
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0] + 1, triangle[1] + 1, triangle[2] + 1];
+        let _ = writeln!(obj, "f {a}//{a} {b}//{b} {c}//{c}");
+    }
+
+    // White diffuse so viewers show the vertex colors unmodified
+    let mtl = "# HeartOn voxel export\nnewmtl voxel\nKa 0 0 0\nKd 1 1 1\nKs 0 0 0\nd 1\nillum 1\n".to_string();
+    (obj, mtl)
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform_cube() -> VoxelScene {
+        let mut scene = VoxelScene::test_cube(1);
+        scene.fill_region([0, 0, 0], [1, 1, 1], [200, 100, 50, 255], 0).unwrap();
+        scene
+    }
+
+    #[test]
+    fn test_scene_mesh_culls_hidden_faces() {
+        // A uniform 2³ cube merges into 6 quads
+        let mesh = scene_mesh(&uniform_cube());
+        assert_eq!(mesh.triangle_count(), 12);
+        assert_eq!(mesh.colors.len(), mesh.vertex_count());
+    }
+
+    #[test]
+    fn test_glb_layout() {
+        let mesh = scene_mesh(&uniform_cube());
+        let glb = write_glb(&mesh, "cube \"2\"").unwrap();
+
+        assert_eq!(&glb[0..4], GLB_MAGIC);
+        assert_eq!(u32::from_le_bytes(glb[8..12].try_into().unwrap()) as usize, glb.len());
+        let json_len = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+        assert_eq!(json_len % 4, 0);
+        assert_eq!(&glb[16..20], GLB_JSON);
+        let json = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+        assert!(json.contains(r#""name":"cube \"2\"""#));
+        assert!(json.contains(r#""COLOR_0":2"#));
+
+        let bin = &glb[20 + json_len..];
+        assert_eq!(&bin[4..8], GLB_BIN);
+        let expected = mesh.vertex_count() * (12 + 12 + 16) + mesh.indices.len() * 4;
+        assert_eq!(u32::from_le_bytes(bin[0..4].try_into().unwrap()) as usize, expected);
+
+        assert!(write_glb(&VoxelMeshData::default(), "empty").is_err());
+    }
+
+    #[test]
+    fn test_obj_counts() {
+        let mesh = scene_mesh(&uniform_cube());
+        let (obj, mtl) = write_obj(&mesh, "cube", "cube.mtl");
+
+        assert!(obj.contains("mtllib cube.mtl"));
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), mesh.vertex_count());
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), mesh.triangle_count());
+        assert_eq!(obj.lines().find(|l| l.starts_with("v ")).unwrap().split(' ').count(), 7);
+        assert!(mtl.contains("newmtl voxel"));
+    }
+}
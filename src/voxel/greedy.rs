@@ -0,0 +1,332 @@
+// SPDX-License-Identifier: MIT
+//! Greedy meshing of voxel chunks
+//!
+//! Faces between two solid voxels are culled. Remaining faces are merged
+//! into the largest rectangles that share a color and material, slice by
+//! slice along each axis. Transparent voxels are meshed per render pass:
+//! their faces are culled by opaque voxels and by voxels of the same pass,
+//! but never hide faces of opaque voxels behind them.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use super::material_registry::VoxelPass;
+use super::mesh_data::{linear_color, VoxelMeshData};
+use super::scene::Voxel;
+
+/// Chunk edge length in voxels
+pub const CHUNK_SIZE: u16 = 32;
+
+const CS: usize = CHUNK_SIZE as usize;
+
+/// Faces can only merge when both color and material match
+type FaceKey = ([u8; 4], u8);
+
+/// Chunk containing a voxel position
+pub fn chunk_coord(position: [u16; 3]) -> IVec3 {
+    IVec3::new(
+        (position[0] / CHUNK_SIZE) as i32,
+        (position[1] / CHUNK_SIZE) as i32,
+        (position[2] / CHUNK_SIZE) as i32,
+    )
+}
+
+/// Voxels of a scene grouped by chunk, plus a solid lookup for face culling
+#[derive(Debug, Default)]
+pub struct ChunkedVoxels {
+    /// Voxels per chunk coordinate
+    pub chunks: HashMap<IVec3, Vec<Voxel>>,
+    /// Every occupied grid position
+    pub solid: HashSet<IVec3>,
+    /// Render pass of every non-opaque voxel
+    pub passes: HashMap<IVec3, VoxelPass>,
+}
+
+impl ChunkedVoxels {
+    /// Group voxels by chunk, treating all of them as opaque
+    pub fn new(voxels: &[Voxel]) -> Self {
+        Self::with_passes(voxels, |_| VoxelPass::Opaque)
+    }
+
+    /// Group voxels by chunk and record each voxel's render pass
+    pub fn with_passes(voxels: &[Voxel], pass_of: impl Fn(&Voxel) -> VoxelPass) -> Self {
+        let mut chunked = Self::default();
+        for voxel in voxels {
+            chunked.chunks.entry(chunk_coord(voxel.position)).or_default().push(*voxel);
+            chunked.solid.insert(grid_pos(voxel.position));
+            let pass = pass_of(voxel);
+            if !pass.is_opaque() {
+                chunked.passes.insert(grid_pos(voxel.position), pass);
+            }
+        }
+        chunked
+    }
+
+    /// Render pass of the voxel at a grid position, if any
+    pub fn pass_at(&self, position: IVec3) -> Option<VoxelPass> {
+        self.solid
+            .contains(&position)
+            .then(|| self.passes.get(&position).copied().unwrap_or_default())
+    }
+
+    /// Render passes present in a chunk, in a stable order
+    pub fn chunk_passes(&self, coord: IVec3) -> Vec<VoxelPass> {
+        let mut passes: Vec<VoxelPass> = self
+            .chunks
+            .get(&coord)
+            .into_iter()
+            .flatten()
+            .map(|v| self.pass_at(grid_pos(v.position)).unwrap_or_default())
+            .collect();
+        passes.sort();
+        passes.dedup();
+        passes
+    }
+
+    /// Voxels of a chunk that belong to a render pass
+    pub fn pass_voxels(&self, coord: IVec3, pass: VoxelPass) -> Vec<Voxel> {
+        self.chunks
+            .get(&coord)
+            .into_iter()
+            .flatten()
+            .filter(|v| self.pass_at(grid_pos(v.position)) == Some(pass))
+            .copied()
+            .collect()
+    }
+
+    /// Greedy-mesh one chunk (empty if the chunk has no visible faces)
+    pub fn mesh_chunk(&self, coord: IVec3) -> VoxelMeshData {
+        let voxels = self.chunks.get(&coord).map_or(&[][..], Vec::as_slice);
+        greedy_mesh_chunk(coord, voxels, |p| self.solid.contains(&p))
+    }
+
+    /// Greedy-mesh the voxels of one render pass in a chunk
+    pub fn mesh_chunk_pass(&self, coord: IVec3, pass: VoxelPass) -> VoxelMeshData {
+        let voxels = self.pass_voxels(coord, pass);
+        greedy_mesh_chunk(coord, &voxels, |p| match self.pass_at(p) {
+            Some(neighbor) => neighbor.is_opaque() || neighbor == pass,
+            None => false,
+        })
+    }
+}
+
+/// Greedy-mesh the voxels of one chunk
+///
+/// `is_solid` answers occupancy for positions not covered by `voxels` (outside
+/// the chunk, or other passes inside it) so faces are culled against those
+/// neighbors. Positions are in scene grid space with voxels centered on their
+/// grid position.
+pub fn greedy_mesh_chunk(coord: IVec3, voxels: &[Voxel], is_solid: impl Fn(IVec3) -> bool) -> VoxelMeshData {
+    let mut mesh = VoxelMeshData::default();
+    if voxels.is_empty() {
+        return mesh;
+    }
+
+    let chunk_min = coord * CHUNK_SIZE as i32;
+
+    // Dense chunk-local occupancy
+    let mut cells: Vec<Option<FaceKey>> = vec![None; CS * CS * CS];
+    for voxel in voxels {
+        let local = grid_pos(voxel.position) - chunk_min;
+        cells[local_index(local)] = Some((voxel.color, voxel.material_id));
+    }
+
+    let solid_at = |local: IVec3| -> bool {
+        let in_chunk = local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(CS as i32)).all();
+        (in_chunk && cells[local_index(local)].is_some()) || is_solid(chunk_min + local)
+    };
+
+    let mut mask: Vec<Option<FaceKey>> = vec![None; CS * CS];
+
+    for axis in 0..3 {
+        let u_axis = (axis + 1) % 3;
+        let v_axis = (axis + 2) % 3;
+
+        for dir in [1, -1] {
+            let normal = IVec3::AXES[axis] * dir;
+
+            for d in 0..CS {
+                // Visible faces in this slice
+                for j in 0..CS {
+                    for i in 0..CS {
+                        let mut local = IVec3::ZERO;
+                        local[axis] = d as i32;
+                        local[u_axis] = i as i32;
+                        local[v_axis] = j as i32;
+
+                        let key = cells[local_index(local)];
+                        mask[j * CS + i] = key.filter(|_| !solid_at(local + normal));
+                    }
+                }
+
+                // Merge into rectangles
+                for j in 0..CS {
+                    let mut i = 0;
+                    while i < CS {
+                        let Some(key) = mask[j * CS + i] else {
+                            i += 1;
+                            continue;
+                        };
+
+                        let mut width = 1;
+                        while i + width < CS && mask[j * CS + i + width] == Some(key) {
+                            width += 1;
+                        }
+
+                        let mut height = 1;
+                        'grow: while j + height < CS {
+                            for k in 0..width {
+                                if mask[(j + height) * CS + i + k] != Some(key) {
+                                    break 'grow;
+                                }
+                            }
+                            height += 1;
+                        }
+
+                        for h in 0..height {
+                            for k in 0..width {
+                                mask[(j + h) * CS + i + k] = None;
+                            }
+                        }
+
+                        let mut origin = chunk_min.as_vec3();
+                        origin[axis] += d as f32 + 0.5 * dir as f32;
+                        origin[u_axis] += i as f32 - 0.5;
+                        origin[v_axis] += j as f32 - 0.5;
+
+                        let mut du = Vec3::ZERO;
+                        du[u_axis] = width as f32;
+                        let mut dv = Vec3::ZERO;
+                        dv[v_axis] = height as f32;
+
+                        push_quad(&mut mesh, origin, du, dv, normal.as_vec3(), dir > 0, key.0);
+                        i += width;
+                    }
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+/// Append a quad spanning `origin..origin + du + dv`
+fn push_quad(
+    mesh: &mut VoxelMeshData,
+    origin: Vec3,
+    du: Vec3,
+    dv: Vec3,
+    normal: Vec3,
+    positive: bool,
+    color: [u8; 4],
+) {
+    let base = mesh.positions.len() as u32;
+    let corners = [origin, origin + du, origin + du + dv, origin + dv];
+    let color = linear_color(color);
+
+    for corner in corners {
+        mesh.positions.push(corner.to_array());
+        mesh.normals.push(normal.to_array());
+        mesh.colors.push(color);
+    }
+
+    // u x v points along +axis; flip winding for faces pointing the other way
+    if positive {
+        mesh.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    } else {
+        mesh.indices.extend_from_slice(&[base, base + 2, base + 1, base, base + 3, base + 2]);
+    }
+}
+
+fn local_index(local: IVec3) -> usize {
+    (local.z as usize * CS + local.y as usize) * CS + local.x as usize
+}
+
+fn grid_pos(position: [u16; 3]) -> IVec3 {
+    IVec3::new(position[0] as i32, position[1] as i32, position[2] as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::VoxelScene;
+
+    fn mesh_scene(scene: &VoxelScene) -> Vec<VoxelMeshData> {
+        let chunked = ChunkedVoxels::new(scene.voxels());
+        chunked.chunks.keys().map(|&c| chunked.mesh_chunk(c)).collect()
+    }
+
+    #[test]
+    fn test_single_voxel_has_six_faces() {
+        let voxels = [Voxel { position: [3, 3, 3], color: [255; 4], material_id: 0 }];
+        let mesh = greedy_mesh_chunk(IVec3::ZERO, &voxels, |_| false);
+        assert_eq!(mesh.triangle_count(), 12);
+        assert_eq!(mesh.vertex_count(), 24);
+    }
+
+    #[test]
+    fn test_uniform_block_merges_to_six_quads() {
+        let voxels: Vec<Voxel> = (0..4)
+            .flat_map(|x| (0..4).flat_map(move |y| (0..4).map(move |z| [x, y, z])))
+            .map(|position| Voxel { position, color: [10, 20, 30, 255], material_id: 0 })
+            .collect();
+        let mesh = greedy_mesh_chunk(IVec3::ZERO, &voxels, |_| false);
+        assert_eq!(mesh.triangle_count(), 12);
+    }
+
+    #[test]
+    fn test_colors_prevent_merging() {
+        // test_cube has a unique color per voxel, so nothing merges
+        let meshes = mesh_scene(&VoxelScene::test_cube(2));
+        let triangles: usize = meshes.iter().map(VoxelMeshData::triangle_count).sum();
+        assert_eq!(triangles, 8 * 3 * 2); // 3 visible faces per corner voxel
+    }
+
+    #[test]
+    fn test_faces_culled_across_chunk_borders() {
+        // Two voxels straddling the x chunk border
+        let scene_voxels = [
+            Voxel { position: [CHUNK_SIZE - 1, 0, 0], color: [255; 4], material_id: 0 },
+            Voxel { position: [CHUNK_SIZE, 0, 0], color: [255; 4], material_id: 0 },
+        ];
+        let chunked = ChunkedVoxels::new(&scene_voxels);
+        assert_eq!(chunked.chunks.len(), 2);
+
+        let triangles: usize = chunked
+            .chunks
+            .keys()
+            .map(|&c| chunked.mesh_chunk(c).triangle_count())
+            .sum();
+        assert_eq!(triangles, 10 * 2); // 12 faces minus the 2 shared ones
+    }
+
+    #[test]
+    fn test_transparent_voxels_do_not_hide_opaque_faces() {
+        let voxels = [
+            Voxel { position: [0, 0, 0], color: [255; 4], material_id: 0 },
+            Voxel { position: [1, 0, 0], color: [0, 0, 255, 128], material_id: 0 },
+            Voxel { position: [2, 0, 0], color: [0, 0, 254, 128], material_id: 0 },
+        ];
+        let chunked = ChunkedVoxels::with_passes(&voxels, |v| {
+            if v.color[3] < 255 { VoxelPass::Blend } else { VoxelPass::Opaque }
+        });
+
+        assert_eq!(chunked.chunk_passes(IVec3::ZERO), [VoxelPass::Opaque, VoxelPass::Blend]);
+        // Opaque voxel keeps its face toward the water
+        assert_eq!(chunked.mesh_chunk_pass(IVec3::ZERO, VoxelPass::Opaque).triangle_count(), 12);
+        // Water drops the face against the opaque voxel and the face between its two voxels
+        assert_eq!(chunked.mesh_chunk_pass(IVec3::ZERO, VoxelPass::Blend).triangle_count(), 9 * 2);
+    }
+
+    #[test]
+    fn test_winding_matches_normals() {
+        let meshes = mesh_scene(&VoxelScene::test_cube(3));
+        for mesh in meshes {
+            for tri in mesh.indices.chunks(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(mesh.positions[tri[i] as usize]));
+                let normal = Vec3::from(mesh.normals[tri[0] as usize]);
+                assert!((b - a).cross(c - a).dot(normal) > 0.0);
+            }
+        }
+    }
+}
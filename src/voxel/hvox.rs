@@ -0,0 +1,1210 @@
+// SPDX-License-Identifier: MIT
+//! .hvox version 2 encoding
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! 0-3    Magic "HVOX"
+//! 4-7    Version = 2 (u32)
+//! 8-11   Section count (u32)
+//! 12-15  Reserved (0)
+//! 16..   Section table, 24 bytes per entry:
+//!          0-3   Tag (4 ASCII bytes)
+//!          4-11  Offset from file start (u64)
+//!          12-19 Length (u64)
+//!          20-23 CRC-32 (IEEE) of the section bytes (u32)
+//! ```
+//!
+//! Sections:
+//! - `META`: width, height, depth (u32), voxel count (u64), origin (3x f32),
+//!   name length (u32) and UTF-8 name
+//! - `PALT`: entry count (u32), then RGBA + material ID (5 bytes) per entry
+//! - `CHNK`: chunk count (u32), then per chunk: chunk coordinate (3x u16),
+//!   voxel count (u32), encoding (u8), payload length (u32) and payload
+//! - `PROP` (optional): property count (u32), then per property: key, value
+//!   type (u8), value length (u32) and value; then marker count (u32) and per
+//!   marker: name and position (3x f32). Strings are a u32 length plus UTF-8.
+//!   Value types: 0 bool (u8), 1 int (i64), 2 float (f64), 3 text, 4 vec3
+//!   (3x f32), 5 list (u32 count, then strings). Unknown types are skipped.
+//!
+//! Chunks cover `CHUNK_EDGE`³ cells in x-fastest order. Encoding 0 is a
+//! run-length list of `(run, value)` LEB128 pairs, where value 0 is empty and
+//! `n` is palette entry `n - 1`. Readers skip sections with unknown tags.
+//! Voxels at the same position collapse to the last one written.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use super::hvox_stream::HvoxStreamParser;
+use super::loader::{HvoxErrorKind, HvoxFormatError, VoxelLoaderError, VoxelValidation};
+use super::scene::{
+    MetadataValue, Voxel, VoxelData, VoxelError, VoxelMarker, VoxelMetadata, VoxelScene,
+};
+
+/// Format version written by [`write_v2`]
+pub const VERSION: u32 = 2;
+
+/// Edge length of a stored chunk in voxels
+pub const CHUNK_EDGE: usize = 32;
+
+/// Cells per stored chunk
+const CHUNK_CELLS: usize = CHUNK_EDGE * CHUNK_EDGE * CHUNK_EDGE;
+
+/// Size of the fixed header before the section table
+const HEADER_SIZE: usize = 16;
+
+/// Size of one section table entry
+const SECTION_ENTRY_SIZE: usize = 24;
+
+/// Chunk payload encodings
+const ENCODING_RLE: u8 = 0;
+
+/// Section tags
+const TAG_META: [u8; 4] = *b"META";
+const TAG_PALETTE: [u8; 4] = *b"PALT";
+const TAG_CHUNKS: [u8; 4] = *b"CHNK";
+const TAG_PROPERTIES: [u8; 4] = *b"PROP";
+
+/// CRC-32 (IEEE 802.3) lookup table
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE) of a byte slice
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !crc32_update(!0, bytes)
+}
+
+/// Continue a CRC-32 over more bytes; start from `!0` and invert the result
+fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(crc, |crc, &b| CRC_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+/// Serialize a scene as .hvox v2
+pub(crate) fn write_v2(scene: &VoxelScene) -> Result<Vec<u8>, String> {
+    let VoxelData::Community(data) = &scene.voxel_data else {
+        return Err("Cannot serialize Professional tier data yet".to_string());
+    };
+
+    // Palette in first-use order
+    let mut palette: Vec<PaletteEntry> = Vec::new();
+    let mut palette_index: HashMap<PaletteEntry, u32> = HashMap::new();
+    for voxel in data.voxels.iter() {
+        palette_index.entry((voxel.color, voxel.material_id)).or_insert_with(|| {
+            palette.push((voxel.color, voxel.material_id));
+            palette.len() as u32 - 1
+        });
+    }
+
+    // Encode chunk by chunk through one cell buffer; the stable sort keeps
+    // later duplicates after earlier ones so they still win
+    let chunk_of = |voxel: &Voxel| voxel.position.map(|c| c / CHUNK_EDGE as u16);
+    let mut order: Vec<usize> = (0..data.voxels.len()).collect();
+    order.sort_by_key(|&i| chunk_of(&data.voxels[i]));
+
+    let mut chunk_section = vec![0; 4];
+    let mut chunk_count: u32 = 0;
+    let mut voxel_count: u64 = 0;
+    let mut cells = vec![0u32; CHUNK_CELLS];
+    for group in order.chunk_by(|&a, &b| chunk_of(&data.voxels[a]) == chunk_of(&data.voxels[b])) {
+        cells.fill(0);
+        for &i in group {
+            let voxel = &data.voxels[i];
+            cells[cell_index(voxel.position)] = palette_index[&(voxel.color, voxel.material_id)] + 1;
+        }
+        let count = cells.iter().filter(|&&c| c != 0).count();
+        voxel_count += count as u64;
+        chunk_count += 1;
+
+        let payload = encode_rle(&cells);
+        for c in chunk_of(&data.voxels[group[0]]) {
+            chunk_section.extend_from_slice(&c.to_le_bytes());
+        }
+        chunk_section.extend_from_slice(&(count as u32).to_le_bytes());
+        chunk_section.push(ENCODING_RLE);
+        chunk_section.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        chunk_section.extend_from_slice(&payload);
+    }
+    chunk_section[..4].copy_from_slice(&chunk_count.to_le_bytes());
+
+    let mut meta = Vec::new();
+    let (width, height, depth) = scene.metadata.dimensions;
+    for value in [width, height, depth] {
+        meta.extend_from_slice(&value.to_le_bytes());
+    }
+    meta.extend_from_slice(&voxel_count.to_le_bytes());
+    for value in scene.metadata.origin.to_array() {
+        meta.extend_from_slice(&value.to_le_bytes());
+    }
+    meta.extend_from_slice(&(scene.metadata.name.len() as u32).to_le_bytes());
+    meta.extend_from_slice(scene.metadata.name.as_bytes());
+
+    let mut palette_section = Vec::with_capacity(4 + palette.len() * 5);
+    palette_section.extend_from_slice(&(palette.len() as u32).to_le_bytes());
+    for (color, material_id) in &palette {
+        palette_section.extend_from_slice(color);
+        palette_section.push(*material_id);
+    }
+
+    let mut sections = vec![(TAG_META, meta), (TAG_PALETTE, palette_section), (TAG_CHUNKS, chunk_section)];
+    if !scene.metadata.properties.is_empty() || !scene.metadata.markers.is_empty() {
+        sections.push((TAG_PROPERTIES, write_properties(&scene.metadata)));
+    }
+    Ok(write_sections(&sections))
+}
+
+/// Encode the `PROP` section
+fn write_properties(metadata: &VoxelMetadata) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(metadata.properties.len() as u32).to_le_bytes());
+    for (key, value) in &metadata.properties {
+        write_string(&mut out, key);
+        let mut body = Vec::new();
+        let kind = match value {
+            MetadataValue::Bool(b) => {
+                body.push(*b as u8);
+                0
+            }
+            MetadataValue::Int(i) => {
+                body.extend_from_slice(&i.to_le_bytes());
+                1
+            }
+            MetadataValue::Float(f) => {
+                body.extend_from_slice(&f.to_le_bytes());
+                2
+            }
+            MetadataValue::Text(text) => {
+                body.extend_from_slice(text.as_bytes());
+                3
+            }
+            MetadataValue::Vec3(v) => {
+                v.to_array().iter().for_each(|c| body.extend_from_slice(&c.to_le_bytes()));
+                4
+            }
+            MetadataValue::List(items) => {
+                body.extend_from_slice(&(items.len() as u32).to_le_bytes());
+                items.iter().for_each(|item| write_string(&mut body, item));
+                5
+            }
+        };
+        out.push(kind);
+        out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        out.extend_from_slice(&body);
+    }
+
+    out.extend_from_slice(&(metadata.markers.len() as u32).to_le_bytes());
+    for marker in &metadata.markers {
+        write_string(&mut out, &marker.name);
+        marker.position.to_array().iter().for_each(|c| out.extend_from_slice(&c.to_le_bytes()));
+    }
+    out
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// Assemble header, section table and section bodies
+fn write_sections(sections: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"HVOX");
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(sections.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+
+    let mut offset = (HEADER_SIZE + sections.len() * SECTION_ENTRY_SIZE) as u64;
+    for (tag, body) in sections {
+        bytes.extend_from_slice(tag);
+        bytes.extend_from_slice(&offset.to_le_bytes());
+        bytes.extend_from_slice(&(body.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&crc32(body).to_le_bytes());
+        offset += body.len() as u64;
+    }
+    for (_, body) in sections {
+        bytes.extend_from_slice(body);
+    }
+    bytes
+}
+
+/// Palette entry: RGBA color and material ID
+pub(crate) type PaletteEntry = ([u8; 4], u8);
+
+/// Size of the header plus section table, from the fixed header
+fn table_end(header: &[u8]) -> u64 {
+    let section_count = u32::from_le_bytes(header[8..12].try_into().unwrap());
+    HEADER_SIZE as u64 + section_count as u64 * SECTION_ENTRY_SIZE as u64
+}
+
+/// One section table entry
+#[derive(Debug, Clone)]
+struct SectionEntry {
+    tag: [u8; 4],
+    /// File offset of the table entry itself
+    entry: u64,
+    range: Range<u64>,
+    crc: u32,
+}
+
+impl SectionEntry {
+    fn start(&self) -> usize {
+        self.range.start as usize
+    }
+
+    fn past_end(&self) -> VoxelLoaderError {
+        invalid_at(format!("Section {} extends past end of file", tag_name(self.tag)), self.entry)
+    }
+
+    /// Section bytes within a whole file
+    fn body<'a>(&self, bytes: &'a [u8]) -> Result<&'a [u8], VoxelLoaderError> {
+        usize::try_from(self.range.start)
+            .ok()
+            .zip(usize::try_from(self.range.end).ok())
+            .and_then(|(start, end)| bytes.get(start..end))
+            .ok_or_else(|| self.past_end())
+    }
+
+    fn check_crc(&self, crc: u32) -> Result<(), VoxelLoaderError> {
+        if crc != self.crc {
+            return Err(HvoxFormatError::new(HvoxErrorKind::Checksum(tag_name(self.tag))).at(self.range.start).into());
+        }
+        Ok(())
+    }
+}
+
+/// Parsed section table: every entry plus the indices of the known sections
+struct SectionTable {
+    entries: Vec<SectionEntry>,
+    meta: usize,
+    palette: usize,
+    chunks: usize,
+    properties: Option<usize>,
+}
+
+impl SectionTable {
+    /// Parse the header and section table bytes, up to [`table_end`]
+    fn read(bytes: &[u8]) -> Result<Self, VoxelLoaderError> {
+        let mut table = Reader::at(&bytes[HEADER_SIZE..], HEADER_SIZE);
+        let mut entries = Vec::new();
+        let (mut meta, mut palette, mut chunks, mut properties) = (None, None, None, None);
+        while !table.is_empty() {
+            let entry = table.offset();
+            let tag: [u8; 4] = table.take(4)?.try_into().unwrap();
+            let offset = table.u64()?;
+            let length = table.u64()?;
+            let crc = table.u32()?;
+            let range = offset..offset.saturating_add(length);
+            let entry = SectionEntry { tag, entry, range, crc };
+            if offset.checked_add(length).is_none() {
+                return Err(entry.past_end());
+            }
+
+            let slot = match tag {
+                TAG_META => &mut meta,
+                TAG_PALETTE => &mut palette,
+                TAG_CHUNKS => &mut chunks,
+                TAG_PROPERTIES => &mut properties,
+                _ => {
+                    debug!("Skipping unknown .hvox section {:?}", String::from_utf8_lossy(&tag));
+                    entries.push(entry);
+                    continue;
+                }
+            };
+            if slot.replace(entries.len()).is_some() {
+                return Err(invalid_at(format!("Duplicate section {}", tag_name(tag)), entry.entry));
+            }
+            entries.push(entry);
+        }
+
+        let missing = |tag| invalid(format!("Missing section {}", tag_name(tag)));
+        Ok(Self {
+            entries,
+            meta: meta.ok_or_else(|| missing(TAG_META))?,
+            palette: palette.ok_or_else(|| missing(TAG_PALETTE))?,
+            chunks: chunks.ok_or_else(|| missing(TAG_CHUNKS))?,
+            properties,
+        })
+    }
+}
+
+/// Raw bodies and file offsets of the sections a v2 reader understands
+pub(crate) struct Sections<'a> {
+    meta: (&'a [u8], usize),
+    palette: (&'a [u8], usize),
+    chunks: (&'a [u8], usize),
+    properties: Option<(&'a [u8], usize)>,
+}
+
+impl<'a> Sections<'a> {
+    /// Locate and checksum the known sections of a v2 file (magic and version already checked)
+    pub(crate) fn read(bytes: &'a [u8]) -> Result<Self, VoxelLoaderError> {
+        let header = bytes
+            .get(..HEADER_SIZE)
+            .ok_or_else(|| HvoxFormatError::new(HvoxErrorKind::TooSmall).at(bytes.len() as u64))?;
+        let table_end = table_end(header);
+        let table = usize::try_from(table_end)
+            .ok()
+            .and_then(|end| bytes.get(..end))
+            .ok_or_else(|| invalid_at("Section table extends past end of file", 8))?;
+        let table = SectionTable::read(table)?;
+
+        for entry in &table.entries {
+            entry.body(bytes)?;
+        }
+        let section = |index: usize| {
+            let entry = &table.entries[index];
+            let body = entry.body(bytes)?;
+            entry.check_crc(crc32(body))?;
+            Ok::<_, VoxelLoaderError>((body, entry.start()))
+        };
+        Ok(Self {
+            meta: section(table.meta)?,
+            palette: section(table.palette)?,
+            chunks: section(table.chunks)?,
+            properties: table.properties.map(section).transpose()?,
+        })
+    }
+
+    /// Scene metadata and the voxel count stored in `META`
+    pub(crate) fn metadata(&self) -> Result<(VoxelMetadata, u64), VoxelLoaderError> {
+        let (mut metadata, voxel_count) = parse_meta(Reader::at(self.meta.0, self.meta.1))?;
+        if let Some((body, offset)) = self.properties {
+            parse_properties(Reader::at(body, offset), &mut metadata)?;
+        }
+        Ok((metadata, voxel_count))
+    }
+
+    /// Decoded `PALT` section
+    pub(crate) fn palette(&self) -> Result<Vec<PaletteEntry>, VoxelLoaderError> {
+        parse_palette(Reader::at(self.palette.0, self.palette.1))
+    }
+
+    /// File range of the `CHNK` section body
+    pub(crate) fn chunk_range(&self) -> Range<usize> {
+        self.chunks.1..self.chunks.1 + self.chunks.0.len()
+    }
+}
+
+/// Parse a whole .hvox v2 file
+///
+/// Strict validation also rejects voxels outside the declared dimensions,
+/// duplicate positions and bytes after the last section.
+pub(crate) fn parse_v2(bytes: &[u8], validation: VoxelValidation) -> Result<VoxelScene, VoxelLoaderError> {
+    let mut parser = HvoxStreamParser::with_validation(validation);
+    parser.feed(bytes)?;
+    parser.finish()
+}
+
+/// Incremental .hvox v2 reader fed with consecutive byte slices
+///
+/// Sections are checksummed as their bytes arrive, and `CHNK` entries are
+/// decoded one at a time once `META` and `PALT` are complete, so only the
+/// current chunk entry is held. A `CHNK` section stored before those (never
+/// the case for [`write_v2`] output) is buffered and decoded at the end.
+///
+/// A stream created with a truncation limit keeps the first voxels up to it
+/// and skips the rest of `CHNK`, instead of rejecting scenes over the tier limit.
+#[derive(Debug, Default)]
+pub(crate) struct V2Stream {
+    /// Voxel count to truncate to instead of failing the tier check
+    truncate_at: Option<u64>,
+    /// Header and section table, until the table is complete
+    head: Vec<u8>,
+    /// File offset of the next byte fed
+    position: u64,
+    /// Table entries in file order, once the table has arrived
+    sections: Vec<StreamSection>,
+    /// End of the last section or of the section table, whichever is later
+    end: u64,
+    /// Scene metadata, declared voxel count and `META` offset
+    metadata: Option<(VoxelMetadata, u64, u64)>,
+    palette: Option<Vec<PaletteEntry>>,
+    chunks: Option<ChunkDecoder>,
+    /// First `CHNK` decoding error, held until the section's checksum is known
+    error: Option<VoxelLoaderError>,
+}
+
+/// Section table entry and the bytes seen of it so far
+#[derive(Debug)]
+struct StreamSection {
+    entry: SectionEntry,
+    /// Whether the tag is one this reader decodes
+    known: bool,
+    crc: u32,
+    received: u64,
+    /// Bytes kept for sections decoded whole
+    body: Vec<u8>,
+}
+
+impl V2Stream {
+    /// Create a stream, optionally truncating to `truncate_at` voxels
+    pub(crate) fn new(truncate_at: Option<usize>) -> Self {
+        Self { truncate_at: truncate_at.map(|limit| limit as u64), ..Default::default() }
+    }
+
+    /// Consume the next bytes of the file
+    ///
+    /// `visit` gets the scene metadata, each decoded voxel and the file range
+    /// of its chunk entry; decoding stops at the first error it returns.
+    pub(crate) fn feed(
+        &mut self,
+        bytes: &[u8],
+        visit: impl FnMut(&VoxelMetadata, Voxel, Range<u64>) -> Result<(), HvoxFormatError>,
+    ) -> Result<(), VoxelLoaderError> {
+        if self.end > 0 {
+            return self.process(bytes, visit);
+        }
+        self.head.extend_from_slice(bytes);
+        if self.head.len() < HEADER_SIZE {
+            return Ok(());
+        }
+        let table_end = table_end(&self.head);
+        if (self.head.len() as u64) < table_end {
+            return Ok(());
+        }
+
+        let table = SectionTable::read(&self.head[..table_end as usize])?;
+        self.end = table.entries.iter().map(|e| e.range.end).fold(table_end, u64::max);
+        let known = [Some(table.meta), Some(table.palette), Some(table.chunks), table.properties];
+        self.sections = table
+            .entries
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| StreamSection {
+                entry,
+                known: known.contains(&Some(index)),
+                crc: !0,
+                received: 0,
+                body: Vec::new(),
+            })
+            .collect();
+        self.sections.sort_by_key(|section| section.entry.range.start);
+        for index in 0..self.sections.len() {
+            if self.sections[index].known && self.sections[index].entry.range.is_empty() {
+                self.complete(index)?;
+            }
+        }
+
+        let head = std::mem::take(&mut self.head);
+        self.process(&head, visit)
+    }
+
+    /// Route bytes at the current position to the sections they belong to
+    fn process(
+        &mut self,
+        bytes: &[u8],
+        mut visit: impl FnMut(&VoxelMetadata, Voxel, Range<u64>) -> Result<(), HvoxFormatError>,
+    ) -> Result<(), VoxelLoaderError> {
+        let start = self.position;
+        self.position += bytes.len() as u64;
+        for index in 0..self.sections.len() {
+            let section = &mut self.sections[index];
+            let range = section.entry.range.start.max(start)..section.entry.range.end.min(self.position);
+            if !section.known || range.is_empty() {
+                continue;
+            }
+            let piece = &bytes[(range.start - start) as usize..(range.end - start) as usize];
+            let first = section.received == 0;
+            section.crc = crc32_update(section.crc, piece);
+            section.received += piece.len() as u64;
+
+            if section.entry.tag == TAG_CHUNKS && first {
+                if let (Some((_, declared, _)), Some(palette)) = (&self.metadata, &self.palette) {
+                    let limit = self.truncate_at.unwrap_or(u64::MAX);
+                    self.chunks = Some(ChunkDecoder::new(palette.clone(), *declared, limit, section.entry.range.start));
+                }
+            }
+            match (&mut self.chunks, &self.metadata) {
+                (Some(decoder), Some((metadata, ..))) if section.entry.tag == TAG_CHUNKS => {
+                    if self.error.is_none() {
+                        let result = decoder.feed(piece, |voxel, chunk| visit(metadata, voxel, chunk));
+                        self.error = result.err();
+                    }
+                }
+                _ => section.body.extend_from_slice(piece),
+            }
+            if section.received == section.entry.range.end - section.entry.range.start {
+                self.complete(index)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify a fully received section and decode it if it is `META` or `PALT`
+    fn complete(&mut self, index: usize) -> Result<(), VoxelLoaderError> {
+        let section = &mut self.sections[index];
+        section.entry.check_crc(!section.crc)?;
+        let offset = section.entry.range.start;
+        match section.entry.tag {
+            TAG_META => {
+                let (metadata, voxel_count) = parse_meta(Reader::at(&section.body, offset))?;
+                if self.truncate_at.is_none() {
+                    check_declared_count(voxel_count)?;
+                }
+                self.metadata = Some((metadata, voxel_count, offset));
+            }
+            TAG_PALETTE => self.palette = Some(parse_palette(Reader::at(&section.body, offset))?),
+            TAG_CHUNKS => {
+                if let Some(error) = self.error.take() {
+                    return Err(error);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Finish once the input is exhausted, returning the scene metadata
+    ///
+    /// Strict validation also rejects bytes after the last section.
+    pub(crate) fn finish(
+        mut self,
+        strict: bool,
+        mut visit: impl FnMut(&VoxelMetadata, Voxel, Range<u64>) -> Result<(), HvoxFormatError>,
+    ) -> Result<VoxelMetadata, VoxelLoaderError> {
+        if self.end == 0 {
+            if self.head.len() < HEADER_SIZE {
+                return Err(HvoxFormatError::new(HvoxErrorKind::TooSmall).at(self.head.len() as u64).into());
+            }
+            return Err(invalid_at("Section table extends past end of file", 8));
+        }
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        if let Some(section) = self.sections.iter().find(|s| s.entry.range.end > self.position) {
+            return Err(section.entry.past_end());
+        }
+        if strict && self.position > self.end {
+            let kind = HvoxErrorKind::TrailingData(self.position - self.end);
+            return Err(HvoxFormatError::new(kind).at(self.end).into());
+        }
+
+        // Every known section is complete and checksummed by now
+        let (mut metadata, voxel_count, meta_offset) = self.metadata.take().ok_or_else(|| invalid("Missing section META"))?;
+        let decoder = match self.chunks.take() {
+            Some(decoder) => decoder,
+            None => {
+                let section = self.sections.iter().find(|s| s.entry.tag == TAG_CHUNKS).unwrap();
+                let palette = self.palette.take().unwrap_or_default();
+                let limit = self.truncate_at.unwrap_or(u64::MAX);
+                let mut decoder = ChunkDecoder::new(palette, voxel_count, limit, section.entry.range.start);
+                decoder.feed(&section.body, |voxel, chunk| visit(&metadata, voxel, chunk))?;
+                decoder
+            }
+        };
+        decoder.finish()?;
+        if decoder.truncated() {
+            warn!(
+                "{}: truncating {} voxels to the {} tier limit ({})",
+                metadata.name,
+                voxel_count,
+                crate::tier::current_tier().name(),
+                decoder.limit
+            );
+        } else if decoder.decoded != voxel_count {
+            return Err(invalid_at(
+                format!("Voxel count mismatch: header says {}, chunks hold {}", voxel_count, decoder.decoded),
+                meta_offset + 12,
+            ));
+        }
+
+        if let Some(section) = self.sections.iter().find(|s| s.entry.tag == TAG_PROPERTIES) {
+            parse_properties(Reader::at(&section.body, section.entry.range.start), &mut metadata)?;
+        }
+        Ok(metadata)
+    }
+}
+
+/// Reject scenes whose declared voxel count exceeds the tier limit before decoding them
+fn check_declared_count(voxel_count: u64) -> Result<(), VoxelLoaderError> {
+    let limit = crate::tier::max_voxels();
+    if voxel_count > limit as u64 {
+        return Err(VoxelError::TierLimitReached {
+            current: usize::try_from(voxel_count).unwrap_or(usize::MAX),
+            limit,
+            tier: crate::tier::current_tier(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Parse the `META` section into metadata and the stored voxel count
+fn parse_meta(mut reader: Reader) -> Result<(VoxelMetadata, u64), VoxelLoaderError> {
+    let dimensions = (reader.u32()?, reader.u32()?, reader.u32()?);
+    let voxel_count = reader.u64()?;
+    let origin = Vec3::new(reader.f32()?, reader.f32()?, reader.f32()?);
+    let name_len = reader.u32()? as usize;
+    let name_offset = reader.offset();
+    let name = std::str::from_utf8(reader.take(name_len)?)
+        .map_err(|_| invalid_at("Scene name is not valid UTF-8", name_offset))?
+        .to_string();
+
+    let metadata = VoxelMetadata { origin, ..VoxelMetadata::new(name, dimensions) };
+    Ok((metadata, voxel_count))
+}
+
+/// Parse the `PROP` section into `metadata`
+fn parse_properties(mut reader: Reader, metadata: &mut VoxelMetadata) -> Result<(), VoxelLoaderError> {
+    let count = reader.u32()?;
+    for _ in 0..count {
+        let key = reader.string()?;
+        let kind = reader.u8()?;
+        let len = reader.u32()? as usize;
+        let mut value = reader.sub(len)?;
+        let start = value.offset();
+        let parsed = match kind {
+            0 => MetadataValue::Bool(value.u8()? != 0),
+            1 => MetadataValue::Int(i64::from_le_bytes(value.take(8)?.try_into().unwrap())),
+            2 => MetadataValue::Float(f64::from_le_bytes(value.take(8)?.try_into().unwrap())),
+            3 => MetadataValue::Text(
+                std::str::from_utf8(value.take(len)?)
+                    .map_err(|_| invalid_at(format!("Property {} is not valid UTF-8", key), start))?
+                    .to_string(),
+            ),
+            4 => MetadataValue::Vec3(Vec3::new(value.f32()?, value.f32()?, value.f32()?)),
+            5 => {
+                let items = value.u32()?;
+                MetadataValue::List((0..items).map(|_| value.string()).collect::<Result<_, _>>()?)
+            }
+            _ => {
+                debug!("Skipping .hvox property {} of unknown type {}", key, kind);
+                continue;
+            }
+        };
+        metadata.properties.insert(key, parsed);
+    }
+
+    let count = reader.u32()?;
+    for _ in 0..count {
+        let name = reader.string()?;
+        let position = Vec3::new(reader.f32()?, reader.f32()?, reader.f32()?);
+        metadata.markers.push(VoxelMarker { name, position });
+    }
+    Ok(())
+}
+
+/// Parse the `PALT` section
+fn parse_palette(mut reader: Reader) -> Result<Vec<PaletteEntry>, VoxelLoaderError> {
+    let count = reader.u32()? as usize;
+    let entries = reader.take(count.checked_mul(5).ok_or_else(|| invalid_at("Palette too large", reader.offset()))?)?;
+    Ok(entries
+        .chunks_exact(5)
+        .map(|e| ([e[0], e[1], e[2], e[3]], e[4]))
+        .collect())
+}
+
+/// Decode a `CHNK` section body starting at file offset `offset`
+///
+/// Fails on the first voxel past `voxel_count`, so a small file of long runs
+/// can't decode into more voxels than its header declares.
+pub(crate) fn visit_chunks(
+    section: &[u8],
+    offset: u64,
+    palette: &[PaletteEntry],
+    voxel_count: u64,
+    visit: impl FnMut(Voxel, Range<u64>) -> Result<(), HvoxFormatError>,
+) -> Result<(), VoxelLoaderError> {
+    let mut decoder = ChunkDecoder::new(palette.to_vec(), voxel_count, u64::MAX, offset);
+    decoder.feed(section, visit)?;
+    decoder.finish()
+}
+
+/// Incremental decoder for a `CHNK` section body, holding at most one chunk entry
+#[derive(Debug)]
+struct ChunkDecoder {
+    palette: Vec<PaletteEntry>,
+    /// Voxel count declared in `META`
+    declared: u64,
+    /// Voxels passed on; decoding stops at the first chunk entry past it
+    limit: u64,
+    decoded: u64,
+    /// Chunk entries still to come, once the chunk count has arrived
+    remaining: Option<u32>,
+    /// Partial chunk count or chunk entry
+    pending: Vec<u8>,
+    /// File offset of `pending[0]`
+    offset: u64,
+    seen: HashSet<[u16; 3]>,
+}
+
+impl ChunkDecoder {
+    /// Size of a chunk entry before its payload
+    const ENTRY_HEADER: usize = 15;
+
+    fn new(palette: Vec<PaletteEntry>, declared: u64, limit: u64, offset: u64) -> Self {
+        let seen = HashSet::new();
+        Self { palette, declared, limit, decoded: 0, remaining: None, pending: Vec::new(), offset, seen }
+    }
+
+    /// Whether voxels past `limit` were dropped
+    fn truncated(&self) -> bool {
+        self.decoded > self.limit
+    }
+
+    /// Bytes `pending` must reach before the chunk count or entry can be decoded
+    fn needed(&self) -> usize {
+        match self.remaining {
+            None => 4,
+            Some(_) if self.pending.len() < Self::ENTRY_HEADER => Self::ENTRY_HEADER,
+            Some(_) => Self::ENTRY_HEADER + u32::from_le_bytes(self.pending[11..15].try_into().unwrap()) as usize,
+        }
+    }
+
+    /// Decode the next section bytes; bytes after the last chunk entry are ignored
+    fn feed(
+        &mut self,
+        mut bytes: &[u8],
+        mut visit: impl FnMut(Voxel, Range<u64>) -> Result<(), HvoxFormatError>,
+    ) -> Result<(), VoxelLoaderError> {
+        while self.remaining != Some(0) {
+            let needed = self.needed();
+            if self.pending.len() < needed {
+                if bytes.is_empty() {
+                    break;
+                }
+                let take = (needed - self.pending.len()).min(bytes.len());
+                self.pending.extend_from_slice(&bytes[..take]);
+                bytes = &bytes[take..];
+                continue;
+            }
+
+            let mut reader = Reader::at(&self.pending, self.offset);
+            match self.remaining {
+                None => self.remaining = Some(reader.u32()?),
+                Some(remaining) => {
+                    let (declared, limit, chunk_offset) = (self.declared, self.limit, self.offset);
+                    let decoded = &mut self.decoded;
+                    visit_chunk(&mut reader, &self.palette, Some(&mut self.seen), |voxel, chunk| {
+                        *decoded += 1;
+                        if *decoded > declared {
+                            let message = format!("Chunks hold more than the {} voxels declared", declared);
+                            return Err(HvoxFormatError::new(HvoxErrorKind::Malformed(message)).at(chunk_offset));
+                        }
+                        if *decoded > limit {
+                            return Ok(());
+                        }
+                        visit(voxel, chunk)
+                    })?;
+                    // Past the limit the remaining entries are skipped
+                    self.remaining = Some(if self.truncated() { 0 } else { remaining - 1 });
+                }
+            }
+            self.offset += self.pending.len() as u64;
+            self.pending.clear();
+        }
+        Ok(())
+    }
+
+    /// Fail if the section ended before its last chunk entry
+    fn finish(&self) -> Result<(), VoxelLoaderError> {
+        if self.remaining == Some(0) {
+            return Ok(());
+        }
+        let kind = HvoxErrorKind::Truncated { needed: self.needed() as u64, available: self.pending.len() as u64 };
+        Err(HvoxFormatError::new(kind).at(self.offset).into())
+    }
+}
+
+/// Decode consecutive chunk entries (without the leading chunk count) found at file offset `offset`
+pub(crate) fn visit_chunk_entries(
+    entries: &[u8],
+    offset: u64,
+    palette: &[PaletteEntry],
+    mut visit: impl FnMut(Voxel),
+) -> Result<(), VoxelLoaderError> {
+    let mut reader = Reader::at(entries, offset);
+    while !reader.is_empty() {
+        visit_chunk(&mut reader, palette, None, |voxel, _| {
+            visit(voxel);
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+/// Decode the chunk entry at the reader, passing each voxel and the entry's file range to `visit`
+///
+/// Chunk coordinates already in `seen` are rejected once the chunk is
+/// decoded, so strict callers report the first duplicate voxel instead.
+fn visit_chunk(
+    reader: &mut Reader,
+    palette: &[PaletteEntry],
+    seen: Option<&mut HashSet<[u16; 3]>>,
+    mut visit: impl FnMut(Voxel, Range<u64>) -> Result<(), HvoxFormatError>,
+) -> Result<(), VoxelLoaderError> {
+    let chunk_offset = reader.offset();
+    let coord = [reader.u16()?, reader.u16()?, reader.u16()?];
+    let expected = reader.u32()? as usize;
+    let encoding = reader.u8()?;
+    let payload_len = reader.u32()? as usize;
+    let payload = reader.sub(payload_len)?;
+    if encoding != ENCODING_RLE {
+        return Err(invalid_at(format!("Unknown chunk encoding {}", encoding), chunk_offset));
+    }
+
+    let span = chunk_offset..reader.offset();
+    let mut count = 0;
+    let base = coord.map(|c| c as usize * CHUNK_EDGE);
+    decode_rle(payload, |cell, value| {
+        let (color, material_id) = *palette
+            .get(value as usize - 1)
+            .ok_or_else(|| invalid_at(format!("Palette index {} out of range", value - 1), chunk_offset))?;
+        let local = [cell % CHUNK_EDGE, cell / CHUNK_EDGE % CHUNK_EDGE, cell / (CHUNK_EDGE * CHUNK_EDGE)];
+        let mut position = [0u16; 3];
+        for axis in 0..3 {
+            position[axis] = u16::try_from(base[axis] + local[axis])
+                .map_err(|_| invalid_at(format!("Chunk {:?} lies outside the voxel grid", coord), chunk_offset))?;
+        }
+        visit(Voxel { position, color, material_id }, span.clone())?;
+        count += 1;
+        Ok(())
+    })?;
+
+    if count != expected {
+        return Err(invalid_at(format!("Chunk {:?} holds {} voxels, expected {}", coord, count, expected), chunk_offset));
+    }
+    if seen.is_some_and(|seen| !seen.insert(coord)) {
+        return Err(invalid_at(format!("Chunk {:?} appears more than once", coord), chunk_offset));
+    }
+    Ok(())
+}
+
+/// Run-length encode chunk cells as LEB128 `(run, value)` pairs
+fn encode_rle(cells: &[u32]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < cells.len() {
+        let value = cells[i];
+        let run = cells[i..].iter().take_while(|&&c| c == value).count();
+        write_varint(&mut out, run as u64);
+        write_varint(&mut out, value as u64);
+        i += run;
+    }
+    out
+}
+
+/// Decode a run-length payload, calling `filled` for every non-empty cell
+fn decode_rle(
+    mut reader: Reader,
+    mut filled: impl FnMut(usize, u32) -> Result<(), VoxelLoaderError>,
+) -> Result<(), VoxelLoaderError> {
+    let start = reader.offset();
+    let mut cell = 0usize;
+    while !reader.is_empty() {
+        let pair = reader.offset();
+        let run = reader.varint()?;
+        let value = u32::try_from(reader.varint()?).map_err(|_| invalid_at("Palette index too large", pair))?;
+        let end = usize::try_from(run)
+            .ok()
+            .and_then(|run| cell.checked_add(run))
+            .filter(|&end| end <= CHUNK_CELLS)
+            .ok_or_else(|| invalid_at("Chunk run overflows chunk", pair))?;
+        if value != 0 {
+            for index in cell..end {
+                filled(index, value)?;
+            }
+        }
+        cell = end;
+    }
+    if cell != CHUNK_CELLS {
+        return Err(invalid_at(format!("Chunk runs cover {} of {} cells", cell, CHUNK_CELLS), start));
+    }
+    Ok(())
+}
+
+/// Index of a position within its chunk (x fastest)
+fn cell_index(position: [u16; 3]) -> usize {
+    let [x, y, z] = position.map(|c| c as usize % CHUNK_EDGE);
+    x + y * CHUNK_EDGE + z * CHUNK_EDGE * CHUNK_EDGE
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn tag_name(tag: [u8; 4]) -> String {
+    String::from_utf8_lossy(&tag).into_owned()
+}
+
+fn invalid(message: impl Into<String>) -> VoxelLoaderError {
+    HvoxErrorKind::Malformed(message.into()).into()
+}
+
+fn invalid_at(message: impl Into<String>, offset: u64) -> VoxelLoaderError {
+    HvoxFormatError::new(HvoxErrorKind::Malformed(message.into())).at(offset).into()
+}
+
+/// Bounds-checked little-endian reader that knows its position in the file
+struct Reader<'a> {
+    bytes: &'a [u8],
+    /// File offset of `bytes[0]`
+    offset: u64,
+}
+
+impl<'a> Reader<'a> {
+    fn at(bytes: &'a [u8], offset: impl TryInto<u64>) -> Self {
+        Self { bytes, offset: offset.try_into().unwrap_or(u64::MAX) }
+    }
+
+    /// File offset of the next byte
+    fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], VoxelLoaderError> {
+        if len > self.bytes.len() {
+            let kind = HvoxErrorKind::Truncated { needed: len as u64, available: self.bytes.len() as u64 };
+            return Err(HvoxFormatError::new(kind).at(self.offset).into());
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        self.offset += len as u64;
+        Ok(head)
+    }
+
+    /// Length-prefixed UTF-8 string
+    fn string(&mut self) -> Result<String, VoxelLoaderError> {
+        let len = self.u32()? as usize;
+        let start = self.offset;
+        std::str::from_utf8(self.take(len)?)
+            .map(str::to_string)
+            .map_err(|_| invalid_at("String is not valid UTF-8", start))
+    }
+
+    /// Split off the next `len` bytes as their own reader
+    fn sub(&mut self, len: usize) -> Result<Reader<'a>, VoxelLoaderError> {
+        let offset = self.offset;
+        Ok(Reader::at(self.take(len)?, offset))
+    }
+
+    fn u8(&mut self) -> Result<u8, VoxelLoaderError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, VoxelLoaderError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, VoxelLoaderError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, VoxelLoaderError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, VoxelLoaderError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn varint(&mut self) -> Result<u64, VoxelLoaderError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid_at("Varint too long", self.offset))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// File whose chunks are each one full run, for decompression bomb tests
+    pub(crate) fn full_chunks_file(declared: u64, coords: &[[u16; 3]]) -> Vec<u8> {
+        let mut meta = Vec::new();
+        [u16::MAX as u32; 3].iter().for_each(|d| meta.extend_from_slice(&d.to_le_bytes()));
+        meta.extend_from_slice(&declared.to_le_bytes());
+        meta.extend_from_slice(&[0; 12]);
+        write_string(&mut meta, "bomb");
+
+        let mut palette = 1u32.to_le_bytes().to_vec();
+        palette.extend_from_slice(&[255, 255, 255, 255, 0]);
+
+        let mut payload = Vec::new();
+        write_varint(&mut payload, CHUNK_CELLS as u64);
+        write_varint(&mut payload, 1);
+        let mut chunks = (coords.len() as u32).to_le_bytes().to_vec();
+        for coord in coords {
+            coord.iter().for_each(|c| chunks.extend_from_slice(&c.to_le_bytes()));
+            chunks.extend_from_slice(&(CHUNK_CELLS as u32).to_le_bytes());
+            chunks.push(ENCODING_RLE);
+            chunks.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            chunks.extend_from_slice(&payload);
+        }
+        write_sections(&[(TAG_META, meta), (TAG_PALETTE, palette), (TAG_CHUNKS, chunks)])
+    }
+
+    #[test]
+    fn test_chunk_runs_bounded_by_declared_count() {
+        let coords: Vec<[u16; 3]> = (0..64).map(|i| [i, 0, 0]).collect();
+        let bytes = full_chunks_file(CHUNK_CELLS as u64, &coords);
+        let error = parse_v2(&bytes, VoxelValidation::Lenient).unwrap_err();
+        assert!(error.to_string().contains("more than the 32768 voxels declared"), "{}", error);
+
+        let repeated = full_chunks_file(CHUNK_CELLS as u64 * 2, &[[0, 0, 0], [0, 0, 0]]);
+        let error = parse_v2(&repeated, VoxelValidation::Lenient).unwrap_err();
+        assert!(error.to_string().contains("appears more than once"), "{}", error);
+
+        // Strict mode names the first duplicate voxel and the repeated chunk's offset
+        let error = parse_v2(&repeated, VoxelValidation::Strict).unwrap_err();
+        let VoxelLoaderError::InvalidFormat(error) = error else { panic!("{}", error) };
+        assert_eq!(error.kind, HvoxErrorKind::Duplicate { position: [0, 0, 0], first: 0 });
+        assert_eq!((error.offset, error.voxel), (Some(160), Some(CHUNK_CELLS)));
+
+        let over_tier = full_chunks_file(u64::MAX, &coords);
+        assert!(matches!(parse_v2(&over_tier, VoxelValidation::Lenient), Err(VoxelLoaderError::TierLimit(_))));
+    }
+
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_v2_roundtrip_compresses() {
+        let mut scene = VoxelScene::test_cube(40);
+        scene.metadata.name = "a scene name longer than the v1 limit".to_string();
+        scene.fill_region([0, 0, 0], [39, 39, 39], [80, 80, 80, 255], 2).unwrap();
+
+        let bytes = write_v2(&scene).unwrap();
+        assert!(bytes.len() < scene.voxel_count() * 11 / 10);
+
+        let parsed = parse_v2(&bytes, VoxelValidation::Lenient).unwrap();
+        assert_eq!(parsed.metadata.name, scene.metadata.name);
+        assert_eq!(parsed.voxel_count(), scene.voxel_count());
+        let mut expected: Vec<_> = scene.voxels().iter().map(|v| (v.position, v.color, v.material_id)).collect();
+        let mut actual: Vec<_> = parsed.voxels().iter().map(|v| (v.position, v.color, v.material_id)).collect();
+        expected.sort();
+        actual.sort();
+        assert_eq!(actual, expected);
+
+        // A later voxel at the same position wins
+        if let VoxelData::Community(data) = &mut scene.voxel_data {
+            std::sync::Arc::make_mut(&mut data.voxels).push(Voxel { position: [0, 0, 0], color: [1, 2, 3, 255], material_id: 0 });
+        }
+        let parsed = parse_v2(&write_v2(&scene).unwrap(), VoxelValidation::Lenient).unwrap();
+        assert_eq!(parsed.voxels().iter().find(|v| v.position == [0, 0, 0]).unwrap().color, [1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn test_properties_and_markers_roundtrip() {
+        let mut scene = VoxelScene::test_cube(2);
+        let metadata = &mut scene.metadata;
+        metadata.set_property("author", "level team");
+        metadata.set_property("revision", 7i64);
+        metadata.set_property("scale", 0.5f64);
+        metadata.set_property("draft", true);
+        metadata.set_property("wind", Vec3::new(1.0, 0.0, -1.0));
+        metadata.set_property("tags", vec!["castle".to_string(), "night".to_string()]);
+        metadata.markers.push(VoxelMarker { name: "spawn".to_string(), position: Vec3::new(1.0, 2.0, 0.5) });
+
+        let bytes = write_v2(&scene).unwrap();
+        let parsed = parse_v2(&bytes, VoxelValidation::Strict).unwrap();
+        assert_eq!(parsed.metadata.properties, scene.metadata.properties);
+        assert_eq!(parsed.metadata.marker("spawn").unwrap().position, Vec3::new(1.0, 2.0, 0.5));
+        assert_eq!(parsed.metadata.property("author").and_then(MetadataValue::as_str), Some("level team"));
+
+        // Scenes without properties don't get the section
+        let plain = write_v2(&VoxelScene::test_cube(2)).unwrap();
+        assert_eq!(u32::from_le_bytes(plain[8..12].try_into().unwrap()), 3);
+
+        // Unknown value types are skipped
+        let mut unknown = Vec::new();
+        unknown.extend_from_slice(&2u32.to_le_bytes());
+        write_string(&mut unknown, "future");
+        unknown.extend_from_slice(&[9, 2, 0, 0, 0, 0xAB, 0xCD]);
+        write_string(&mut unknown, "ok");
+        unknown.extend_from_slice(&[0, 1, 0, 0, 0, 1]);
+        unknown.extend_from_slice(&0u32.to_le_bytes());
+        let mut metadata = VoxelScene::test_cube(1).metadata;
+        parse_properties(Reader::at(&unknown, 0), &mut metadata).unwrap();
+        assert_eq!(metadata.properties.len(), 1);
+        assert_eq!(metadata.property("ok"), Some(&MetadataValue::Bool(true)));
+    }
+
+    #[test]
+    fn test_unknown_sections_skipped_and_crc_checked() {
+        let scene = VoxelScene::test_cube(2);
+        let bytes = write_v2(&scene).unwrap();
+
+        // Re-assemble with an extra section in front
+        let body = |tag: [u8; 4]| {
+            let mut table = Reader::at(&bytes[HEADER_SIZE..], HEADER_SIZE);
+            loop {
+                let entry_tag: [u8; 4] = table.take(4).unwrap().try_into().unwrap();
+                let (offset, length) = (table.u64().unwrap() as usize, table.u64().unwrap() as usize);
+                table.u32().unwrap();
+                if entry_tag == tag {
+                    return bytes[offset..offset + length].to_vec();
+                }
+            }
+        };
+        let mut with_extra = write_sections(&[
+            (*b"XTRA", vec![1, 2, 3]),
+            (TAG_META, body(TAG_META)),
+            (TAG_PALETTE, body(TAG_PALETTE)),
+            (TAG_CHUNKS, body(TAG_CHUNKS)),
+        ]);
+        assert_eq!(parse_v2(&with_extra, VoxelValidation::Lenient).unwrap().voxel_count(), 8);
+
+        // Chunks stored before the palette are buffered and decoded at the end
+        let reordered = write_sections(&[
+            (TAG_CHUNKS, body(TAG_CHUNKS)),
+            (TAG_META, body(TAG_META)),
+            (TAG_PALETTE, body(TAG_PALETTE)),
+        ]);
+        assert_eq!(parse_v2(&reordered, VoxelValidation::Strict).unwrap().voxel_count(), 8);
+
+        // Strict mode rejects bytes after the last section
+        let mut trailing = with_extra.clone();
+        trailing.extend_from_slice(&[0; 5]);
+        assert!(parse_v2(&trailing, VoxelValidation::Lenient).is_ok());
+        let error = parse_v2(&trailing, VoxelValidation::Strict).unwrap_err();
+        assert!(matches!(
+            error,
+            VoxelLoaderError::InvalidFormat(HvoxFormatError { kind: HvoxErrorKind::TrailingData(5), offset: Some(o), .. }) if o as usize == with_extra.len()
+        ));
+
+        // Corrupt the last byte of the chunk section
+        *with_extra.last_mut().unwrap() ^= 0xFF;
+        let error = parse_v2(&with_extra, VoxelValidation::Lenient).unwrap_err();
+        assert!(
+            matches!(&error, VoxelLoaderError::InvalidFormat(HvoxFormatError { kind: HvoxErrorKind::Checksum(tag), offset: Some(_), .. }) if tag == "CHNK"),
+            "{}",
+            error
+        );
+    }
+}
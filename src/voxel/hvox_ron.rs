@@ -0,0 +1,202 @@
+// SPDX-License-Identifier: MIT
+//! Human-readable `.hvox.ron` voxel scenes for tests and hand-authored fixtures
+//!
+//! A file holds the scene metadata plus voxels, listed one by one in `data`
+//! and/or as runs along +X in `rows`. `voxel_count` is recomputed on load,
+//! and `origin`, `voxel_size` and `voxel_count` may be omitted.
+//!
+//! ```ron
+//! (
+//!     metadata: (name: "steps", dimensions: (4, 2, 1)),
+//!     data: Community((voxels: [
+//!         (position: (0, 1, 0), color: (255, 0, 0, 255), material_id: 1),
+//!     ])),
+//!     rows: [
+//!         (start: (0, 0, 0), length: 4, color: (90, 90, 90, 255), material_id: 0),
+//!     ],
+//! )
+//! ```
+
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+
+use super::loader::{apply_loader_settings, VoxelLoaderError, VoxelSceneLoaderSettings};
+use super::scene::{Voxel, VoxelData, VoxelMetadata, VoxelScene};
+
+/// Asset loader for `.hvox.ron` voxel scene files
+#[derive(Default)]
+pub struct VoxelSceneRonLoader;
+
+/// Run of identical voxels along +X
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoxelRow {
+    /// Position of the first voxel
+    pub start: [u16; 3],
+    /// Number of voxels in the run
+    pub length: u16,
+    /// RGBA color
+    pub color: [u8; 4],
+    /// Material ID
+    pub material_id: u8,
+}
+
+/// On-disk layout of a `.hvox.ron` file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RonScene {
+    metadata: VoxelMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<VoxelData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    rows: Vec<VoxelRow>,
+}
+
+/// Serialize a scene as `.hvox.ron`, as runs along +X when `rows` is set
+pub(crate) fn write_ron(scene: &VoxelScene, rows: bool) -> Result<String, String> {
+    let file = if rows {
+        let VoxelData::Community(data) = &scene.voxel_data else {
+            return Err("Cannot serialize Professional tier data as rows".to_string());
+        };
+        RonScene { metadata: scene.metadata.clone(), data: None, rows: voxel_rows(&data.voxels) }
+    } else {
+        RonScene { metadata: scene.metadata.clone(), data: Some(scene.voxel_data.clone()), rows: Vec::new() }
+    };
+    let config = ron::ser::PrettyConfig::new().depth_limit(4);
+    ron_options().to_string_pretty(&file, config).map_err(|e| e.to_string())
+}
+
+/// Parse `.hvox.ron` text into a scene
+pub fn parse_ron(text: &str) -> Result<VoxelScene, VoxelLoaderError> {
+    let file: RonScene = ron_options().from_str(text).map_err(|e| VoxelLoaderError::InvalidRon(e.to_string()))?;
+
+    let mut voxels = match file.data {
+        Some(VoxelData::Community(data)) => std::sync::Arc::unwrap_or_clone(data.voxels),
+        Some(VoxelData::Professional(_)) => {
+            return Err(VoxelLoaderError::InvalidRon("Professional tier data is not supported".to_string()))
+        }
+        None => Vec::new(),
+    };
+    for row in &file.rows {
+        let [x, y, z] = row.start;
+        if x as u32 + row.length as u32 > u16::MAX as u32 + 1 {
+            return Err(VoxelLoaderError::InvalidRon(format!("Row at {:?} runs past the voxel grid", row.start)));
+        }
+        voxels.extend((0..row.length).map(|i| Voxel {
+            position: [x + i, y, z],
+            color: row.color,
+            material_id: row.material_id,
+        }));
+    }
+
+    Ok(VoxelScene::from_voxels(file.metadata, voxels))
+}
+
+/// `data` is written without `Some(...)`
+fn ron_options() -> ron::Options {
+    ron::Options::default().with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+}
+
+/// Merge voxels into runs of the same color and material along +X
+fn voxel_rows(voxels: &[Voxel]) -> Vec<VoxelRow> {
+    let mut sorted = voxels.to_vec();
+    sorted.sort_by_key(|v| (v.position[2], v.position[1], v.position[0]));
+
+    let mut rows: Vec<VoxelRow> = Vec::new();
+    for voxel in sorted {
+        if let Some(row) = rows.last_mut() {
+            let [x, y, z] = row.start;
+            let continues = voxel.position == [x + row.length, y, z]
+                && voxel.color == row.color
+                && voxel.material_id == row.material_id
+                && row.length < u16::MAX;
+            if continues {
+                row.length += 1;
+                continue;
+            }
+            // Duplicates collapse into the row already covering them
+            if voxel.position[1..] == row.start[1..] && voxel.position[0] < x + row.length {
+                continue;
+            }
+        }
+        rows.push(VoxelRow { start: voxel.position, length: 1, color: voxel.color, material_id: voxel.material_id });
+    }
+    rows
+}
+
+impl AssetLoader for VoxelSceneRonLoader {
+    type Asset = VoxelScene;
+    type Settings = VoxelSceneLoaderSettings;
+    type Error = VoxelLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).await?;
+
+            let scene = apply_loader_settings(parse_ron(&text)?, settings)?;
+            info!("Loaded voxel scene: {} ({} voxels)", scene.metadata.name, scene.voxel_count());
+            Ok(scene)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["hvox.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(scene: &VoxelScene) -> Vec<([u16; 3], [u8; 4], u8)> {
+        let mut voxels: Vec<_> = scene.voxels().iter().map(|v| (v.position, v.color, v.material_id)).collect();
+        voxels.sort();
+        voxels
+    }
+
+    #[test]
+    fn test_roundtrip_list_and_rows() {
+        let mut scene = VoxelScene::test_cube(3);
+        scene.metadata.origin = Vec3::new(1.0, 2.0, 3.0);
+        scene.fill_region([0, 0, 0], [2, 0, 2], [10, 20, 30, 255], 4).unwrap();
+
+        for rows in [false, true] {
+            let text = scene.to_hvox_ron(rows).unwrap();
+            let parsed = parse_ron(&text).unwrap();
+            assert_eq!(parsed.metadata.origin, scene.metadata.origin);
+            assert_eq!(parsed.voxel_count(), scene.voxel_count());
+            assert_eq!(sorted(&parsed), sorted(&scene));
+        }
+
+        // Three rows of three merge along X on the filled floor
+        let rows = scene.to_hvox_ron(true).unwrap();
+        assert!(rows.matches("length: 3").count() >= 3);
+    }
+
+    #[test]
+    fn test_parse_fixture() {
+        let scene = parse_ron(
+            r#"(
+                metadata: (name: "steps", dimensions: (4, 2, 1)),
+                data: Community((voxels: [
+                    (position: (0, 1, 0), color: (255, 0, 0, 255), material_id: 1),
+                ])),
+                rows: [(start: (0, 0, 0), length: 4, color: (90, 90, 90, 255), material_id: 0)],
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(scene.voxel_count(), 5);
+        assert_eq!(scene.metadata.voxel_size, 1.0);
+        assert!(scene.voxels().iter().any(|v| v.position == [3, 0, 0]));
+
+        let overflow = r#"(metadata: (name: "x", dimensions: (1, 1, 1)), rows: [(start: (65535, 0, 0), length: 2, color: (0, 0, 0, 0), material_id: 0)])"#;
+        assert!(matches!(parse_ron(overflow), Err(VoxelLoaderError::InvalidRon(_))));
+    }
+}
@@ -0,0 +1,478 @@
+// SPDX-License-Identifier: MIT
+//! Incremental and memory-mapped .hvox reading for large files
+//!
+//! [`HvoxStreamParser`] decodes version 1 voxel records as bytes arrive, so
+//! loading never holds the raw file and the decoded voxels at once. Version
+//! 2 sections are checksummed as they stream in, and chunks are decoded one
+//! entry at a time once the metadata and palette have arrived.
+//!
+//! In strict mode the parser rejects out-of-bounds and duplicate voxels as
+//! they are decoded, and trailing bytes once the input ends, reporting the
+//! byte offset and voxel index of the problem.
+//!
+//! [`HvoxView`] maps a file into memory for tools that only scan or count
+//! voxels: records are decoded one at a time straight from the mapping.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use super::hvox::{self, PaletteEntry, Sections, V2Stream};
+use super::loader::{
+    check_bounds, check_header, decode_voxel, format, parse_hvox_metadata, HvoxErrorKind, HvoxFormatError,
+    TierLimitPolicy, VoxelLoaderError, VoxelValidation,
+};
+use super::scene::{Voxel, VoxelMetadata, VoxelScene};
+
+/// Bytes requested from the asset reader per read
+pub const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Voxels reserved up front, whatever the header claims
+const MAX_RESERVED_VOXELS: usize = 1 << 20;
+
+/// Incremental .hvox parser fed with consecutive byte slices
+///
+/// ```rust
+/// use hearton_public::voxel::hvox_stream::HvoxStreamParser;
+/// use hearton_public::voxel::VoxelScene;
+///
+/// let bytes = VoxelScene::test_cube(4).to_hvox().unwrap();
+/// let mut parser = HvoxStreamParser::new();
+/// for piece in bytes.chunks(100) {
+///     parser.feed(piece).unwrap();
+/// }
+/// assert_eq!(parser.finish().unwrap().voxel_count(), 64);
+/// ```
+#[derive(Debug, Default)]
+pub struct HvoxStreamParser {
+    /// Header bytes or a partial voxel record
+    pending: Vec<u8>,
+    state: StreamState,
+    checks: StrictChecks,
+    /// Voxel count version 2 decoding stops at, instead of failing the tier check
+    truncate_at: Option<usize>,
+}
+
+/// Per-voxel checks applied while decoding in strict mode
+#[derive(Debug, Default)]
+struct StrictChecks {
+    enabled: bool,
+    first_at: HashMap<[u16; 3], usize>,
+    trailing: u64,
+}
+
+impl StrictChecks {
+    /// Check the voxel at `index`, decoded from the record or chunk entry at `offset`
+    fn accept(
+        &mut self,
+        voxel: &Voxel,
+        index: usize,
+        offset: u64,
+        metadata: &VoxelMetadata,
+    ) -> Result<(), HvoxFormatError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let located = |kind| HvoxFormatError::new(kind).at(offset).voxel(index);
+        check_bounds(voxel.position, metadata.dimensions).map_err(located)?;
+        if let Some(&first) = self.first_at.get(&voxel.position) {
+            return Err(located(HvoxErrorKind::Duplicate { position: voxel.position, first }));
+        }
+        self.first_at.insert(voxel.position, index);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+enum StreamState {
+    #[default]
+    Header,
+    V1 {
+        metadata: VoxelMetadata,
+        voxels: Vec<Voxel>,
+    },
+    V2 {
+        stream: Box<V2Stream>,
+        voxels: Vec<Voxel>,
+    },
+}
+
+impl HvoxStreamParser {
+    /// Create a lenient parser expecting the start of a file
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a parser with the given validation mode
+    pub fn with_validation(validation: VoxelValidation) -> Self {
+        let checks = StrictChecks { enabled: validation == VoxelValidation::Strict, ..Default::default() };
+        Self { checks, ..Default::default() }
+    }
+
+    /// Truncate version 2 scenes over the tier limit while decoding under [`TierLimitPolicy::Truncate`]
+    ///
+    /// Version 1 scenes are decoded whole and truncated by the loader afterwards.
+    pub fn with_tier_limit(mut self, policy: TierLimitPolicy) -> Self {
+        self.truncate_at = (policy == TierLimitPolicy::Truncate).then(crate::tier::max_voxels);
+        self
+    }
+
+    /// Consume the next bytes of the file
+    pub fn feed(&mut self, mut bytes: &[u8]) -> Result<(), VoxelLoaderError> {
+        if let StreamState::Header = self.state {
+            let needed = format::HEADER_SIZE.saturating_sub(self.pending.len()).min(bytes.len());
+            self.pending.extend_from_slice(&bytes[..needed]);
+            bytes = &bytes[needed..];
+            if self.pending.len() < 8 {
+                return Ok(());
+            }
+
+            if check_header(&self.pending)? == hvox::VERSION {
+                self.state = StreamState::V2 { stream: Box::new(V2Stream::new(self.truncate_at)), voxels: Vec::new() };
+            } else if self.pending.len() == format::HEADER_SIZE {
+                let metadata = parse_hvox_metadata(&self.pending)?;
+                let voxels = Vec::with_capacity(metadata.voxel_count.min(MAX_RESERVED_VOXELS));
+                self.pending.clear();
+                self.state = StreamState::V1 { metadata, voxels };
+            } else {
+                return Ok(());
+            }
+        }
+
+        match &mut self.state {
+            StreamState::Header => {}
+            StreamState::V2 { stream, voxels } => {
+                let checks = &mut self.checks;
+                let mut visit = |metadata: &VoxelMetadata, voxel: Voxel, chunk: Range<u64>| {
+                    checks.accept(&voxel, voxels.len(), chunk.start, metadata)?;
+                    voxels.push(voxel);
+                    Ok(())
+                };
+                // Header bytes read before the version was known
+                if !self.pending.is_empty() {
+                    stream.feed(&std::mem::take(&mut self.pending), &mut visit)?;
+                }
+                stream.feed(bytes, visit)?;
+            }
+            StreamState::V1 { metadata, voxels } => {
+                // Complete a record split across feeds
+                if !self.pending.is_empty() {
+                    let needed = (format::VOXEL_SIZE - self.pending.len()).min(bytes.len());
+                    self.pending.extend_from_slice(&bytes[..needed]);
+                    bytes = &bytes[needed..];
+                    if self.pending.len() < format::VOXEL_SIZE {
+                        return Ok(());
+                    }
+                    let voxel = decode_voxel(&self.pending);
+                    self.checks.accept(&voxel, voxels.len(), record_offset(voxels.len()), metadata)?;
+                    voxels.push(voxel);
+                    self.pending.clear();
+                }
+
+                let wanted = metadata.voxel_count - voxels.len();
+                let records = bytes.chunks_exact(format::VOXEL_SIZE);
+                let remainder = records.remainder();
+                let taken = records.len().min(wanted);
+                for record in records.take(wanted) {
+                    let voxel = decode_voxel(record);
+                    self.checks.accept(&voxel, voxels.len(), record_offset(voxels.len()), metadata)?;
+                    voxels.push(voxel);
+                }
+                if voxels.len() < metadata.voxel_count {
+                    self.pending.extend_from_slice(remainder);
+                } else {
+                    self.checks.trailing += (bytes.len() - taken * format::VOXEL_SIZE) as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish parsing once the input is exhausted
+    pub fn finish(self) -> Result<VoxelScene, VoxelLoaderError> {
+        match self.state {
+            StreamState::Header => {
+                // Report a bad version before the short header
+                let version = check_header(&self.pending)?;
+                if version != format::VERSION {
+                    return Err(HvoxFormatError::new(HvoxErrorKind::UnsupportedVersion(version)).at(4).into());
+                }
+                Err(HvoxFormatError::new(HvoxErrorKind::TooSmall).at(self.pending.len() as u64).into())
+            }
+            StreamState::V2 { stream, mut voxels } => {
+                let mut checks = self.checks;
+                let strict = checks.enabled;
+                let metadata = stream.finish(strict, |metadata, voxel, chunk| {
+                    checks.accept(&voxel, voxels.len(), chunk.start, metadata)?;
+                    voxels.push(voxel);
+                    Ok(())
+                })?;
+                Ok(VoxelScene::from_voxels(metadata, voxels))
+            }
+            StreamState::V1 { metadata, voxels } => {
+                if voxels.len() < metadata.voxel_count {
+                    let kind = HvoxErrorKind::Truncated {
+                        needed: format::VOXEL_SIZE as u64,
+                        available: self.pending.len() as u64,
+                    };
+                    return Err(HvoxFormatError::new(kind).at(record_offset(voxels.len())).voxel(voxels.len()).into());
+                }
+                if self.checks.enabled && self.checks.trailing > 0 {
+                    let kind = HvoxErrorKind::TrailingData(self.checks.trailing);
+                    return Err(HvoxFormatError::new(kind).at(record_offset(voxels.len())).into());
+                }
+                Ok(VoxelScene::from_voxels(metadata, voxels))
+            }
+        }
+    }
+}
+
+/// Read-only, memory-mapped view of a .hvox file
+///
+/// Opening only reads the header (and section table for version 2); voxels
+/// are decoded on demand from the mapping.
+#[derive(Debug)]
+pub struct HvoxView {
+    map: Mmap,
+    version: u32,
+    metadata: VoxelMetadata,
+    /// Palette and `CHNK` body range of a version 2 file
+    chunks: Option<(Vec<PaletteEntry>, Range<usize>)>,
+}
+
+impl HvoxView {
+    /// Map a file and read its metadata
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, VoxelLoaderError> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is read-only; like every mmap reader we rely on
+        // the file not being truncated or rewritten while the view is alive.
+        let map = unsafe { Mmap::map(&file)? };
+
+        let version = check_header(&map)?;
+        let (metadata, chunks) = if version == hvox::VERSION {
+            let sections = Sections::read(&map)?;
+            let (mut metadata, voxel_count) = sections.metadata()?;
+            metadata.voxel_count = usize::try_from(voxel_count)
+                .map_err(|_| HvoxErrorKind::Malformed(format!("Voxel count {} too large", voxel_count)))?;
+            (metadata, Some((sections.palette()?, sections.chunk_range())))
+        } else {
+            let header = map
+                .get(..format::HEADER_SIZE)
+                .ok_or_else(|| HvoxFormatError::new(HvoxErrorKind::TooSmall).at(map.len() as u64))?;
+            let metadata = parse_hvox_metadata(header)?;
+            let needed = (metadata.voxel_count * format::VOXEL_SIZE) as u64;
+            let available = (map.len() - format::HEADER_SIZE) as u64;
+            if available < needed {
+                let kind = HvoxErrorKind::Truncated { needed, available };
+                return Err(HvoxFormatError::new(kind).at(format::HEADER_SIZE as u64).into());
+            }
+            (metadata, None)
+        };
+        Ok(Self { map, version, metadata, chunks })
+    }
+
+    /// Format version of the file
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Scene metadata from the header
+    pub fn metadata(&self) -> &VoxelMetadata {
+        &self.metadata
+    }
+
+    /// Voxel count stored in the header
+    pub fn voxel_count(&self) -> usize {
+        self.metadata.voxel_count
+    }
+
+    /// Voxel at `index` in file order, without decoding the others
+    ///
+    /// Only version 1 files have fixed-size records; version 2 returns `None`.
+    pub fn voxel(&self, index: usize) -> Option<Voxel> {
+        if self.version == hvox::VERSION || index >= self.voxel_count() {
+            return None;
+        }
+        let start = format::HEADER_SIZE + index * format::VOXEL_SIZE;
+        Some(decode_voxel(&self.map[start..start + format::VOXEL_SIZE]))
+    }
+
+    /// Visit every voxel in file order without collecting them
+    pub fn for_each_voxel(&self, mut visit: impl FnMut(Voxel)) -> Result<(), VoxelLoaderError> {
+        self.for_each_voxel_with_range(|voxel, _| visit(voxel))
+    }
+
+    /// Visit every voxel along with the byte range it is decoded from
+    ///
+    /// Ranges are single records in version 1 and whole chunk entries in
+    /// version 2. Adjacent ranges can be merged and decoded again later with
+    /// [`Self::for_each_voxel_in`].
+    pub fn for_each_voxel_with_range(&self, mut visit: impl FnMut(Voxel, Range<u64>)) -> Result<(), VoxelLoaderError> {
+        if let Some((palette, chunks)) = &self.chunks {
+            let declared = self.voxel_count() as u64;
+            return hvox::visit_chunks(&self.map[chunks.clone()], chunks.start as u64, palette, declared, |voxel, range| {
+                visit(voxel, range);
+                Ok(())
+            });
+        }
+        let end = format::HEADER_SIZE + self.voxel_count() * format::VOXEL_SIZE;
+        for (index, record) in self.map[format::HEADER_SIZE..end].chunks_exact(format::VOXEL_SIZE).enumerate() {
+            visit(decode_voxel(record), record_offset(index)..record_offset(index + 1));
+        }
+        Ok(())
+    }
+
+    /// Decode the voxels in a byte range built from [`Self::for_each_voxel_with_range`]
+    pub fn for_each_voxel_in(&self, range: Range<u64>, mut visit: impl FnMut(Voxel)) -> Result<(), VoxelLoaderError> {
+        let invalid = || HvoxFormatError::new(HvoxErrorKind::Malformed(format!("Invalid voxel range {:?}", range))).at(range.start);
+        let (start, end) = (range.start as usize, range.end as usize);
+
+        if let Some((palette, chunks)) = &self.chunks {
+            if start < chunks.start || end > chunks.end || start > end {
+                return Err(invalid().into());
+            }
+            return hvox::visit_chunk_entries(&self.map[start..end], range.start, palette, visit);
+        }
+        let records = record_offset(0) as usize..record_offset(self.voxel_count()) as usize;
+        let aligned = |offset: usize| (offset - records.start) % format::VOXEL_SIZE == 0;
+        if start < records.start || end > records.end || start > end || !aligned(start) || !aligned(end) {
+            return Err(invalid().into());
+        }
+        self.map[start..end].chunks_exact(format::VOXEL_SIZE).for_each(|record| visit(decode_voxel(record)));
+        Ok(())
+    }
+}
+
+/// Byte offset of a version 1 voxel record
+fn record_offset(index: usize) -> u64 {
+    (format::HEADER_SIZE + index * format::VOXEL_SIZE) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn positions(voxels: impl IntoIterator<Item = Voxel>) -> Vec<[u16; 3]> {
+        let mut positions: Vec<_> = voxels.into_iter().map(|v| v.position).collect();
+        positions.sort();
+        positions
+    }
+
+    #[test]
+    fn test_stream_any_split() {
+        let scene = VoxelScene::test_cube(3);
+        for version in [1, 2] {
+            let bytes = scene.to_hvox_with_version(version).unwrap();
+            for piece in [1, 7, 11, 64, 1000] {
+                let mut parser = HvoxStreamParser::new();
+                for part in bytes.chunks(piece) {
+                    parser.feed(part).unwrap();
+                }
+                let parsed = parser.finish().unwrap();
+                assert_eq!(parsed.metadata.name, scene.metadata.name);
+                assert_eq!(positions(parsed.voxels().iter().copied()), positions(scene.voxels().iter().copied()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        let bytes = VoxelScene::test_cube(2).to_hvox().unwrap();
+
+        let mut truncated = HvoxStreamParser::new();
+        truncated.feed(&bytes[..bytes.len() - 3]).unwrap();
+        assert!(truncated.finish().is_err());
+
+        let mut short = HvoxStreamParser::new();
+        short.feed(&bytes[..20]).unwrap();
+        assert!(short.finish().is_err());
+
+        assert!(HvoxStreamParser::new().feed(b"NOPE\x01\0\0\0").is_err());
+    }
+
+    #[test]
+    fn test_stream_v2_decodes_chunks_as_they_arrive() {
+        let mut scene = VoxelScene::test_cube(64);
+        scene.fill_region([0, 0, 0], [63, 63, 63], [80, 80, 80, 255], 1).unwrap();
+        let bytes = scene.to_hvox_with_version(2).unwrap();
+
+        // Everything but the last chunk entry is decoded before the input ends
+        let mut parser = HvoxStreamParser::new();
+        for part in bytes[..bytes.len() - 1].chunks(100) {
+            parser.feed(part).unwrap();
+        }
+        let StreamState::V2 { voxels, .. } = &parser.state else { panic!("expected a v2 stream") };
+        assert_eq!(voxels.len(), 7 * 32 * 32 * 32);
+        assert!(parser.pending.is_empty());
+        parser.feed(&bytes[bytes.len() - 1..]).unwrap();
+        assert_eq!(parser.finish().unwrap().voxel_count(), 64 * 64 * 64);
+
+        // A corrupt META is reported as soon as the section is complete
+        let mut corrupt = bytes.clone();
+        let meta = u64::from_le_bytes(corrupt[20..28].try_into().unwrap()) as usize;
+        corrupt[meta] ^= 0xFF;
+        let error = HvoxStreamParser::new().feed(&corrupt[..meta + 100]).unwrap_err();
+        assert!(
+            matches!(&error, VoxelLoaderError::InvalidFormat(HvoxFormatError { kind: HvoxErrorKind::Checksum(tag), .. }) if tag == "META"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_stream_v2_truncates_to_tier_limit() {
+        // Declares far more voxels than any tier allows; 64 chunks of 32768 follow
+        let coords: Vec<[u16; 3]> = (0..64).map(|i| [i, 0, 0]).collect();
+        let bytes = hvox::tests::full_chunks_file(u64::MAX, &coords);
+
+        let mut reject = HvoxStreamParser::new();
+        assert!(matches!(reject.feed(&bytes), Err(VoxelLoaderError::TierLimit(_))));
+
+        for piece in [bytes.len(), 1000] {
+            let mut parser = HvoxStreamParser::new().with_tier_limit(TierLimitPolicy::Truncate);
+            // Stand-in for the tier limit, which is too large to decode in a test
+            parser.truncate_at = Some(40_000);
+            for part in bytes.chunks(piece) {
+                parser.feed(part).unwrap();
+            }
+            let scene = parser.finish().unwrap();
+            assert_eq!(scene.voxel_count(), 40_000);
+            assert_eq!(scene.voxels()[0].position, [0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn test_view_scans_mapped_file() {
+        let scene = VoxelScene::test_cube(3);
+        for version in [1, 2] {
+            let mut file = tempfile::NamedTempFile::new().unwrap();
+            file.write_all(&scene.to_hvox_with_version(version).unwrap()).unwrap();
+
+            let view = HvoxView::open(file.path()).unwrap();
+            assert_eq!(view.version(), version);
+            assert_eq!(view.voxel_count(), 27);
+            let mut visited = Vec::new();
+            view.for_each_voxel(|v| visited.push(v)).unwrap();
+            assert_eq!(positions(visited), positions(scene.voxels().iter().copied()));
+            assert_eq!(view.voxel(0).is_some(), version == 1);
+
+            // Ranges decode back to the voxels they were reported with
+            let mut ranges: Vec<Range<u64>> = Vec::new();
+            view.for_each_voxel_with_range(|_, range| match ranges.last_mut() {
+                Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+                _ => ranges.push(range),
+            })
+            .unwrap();
+            let mut decoded = Vec::new();
+            for range in ranges {
+                view.for_each_voxel_in(range, |v| decoded.push(v)).unwrap();
+            }
+            assert_eq!(positions(decoded), positions(scene.voxels().iter().copied()));
+            assert!(view.for_each_voxel_in(0..3, |_| {}).is_err());
+        }
+    }
+}
@@ -0,0 +1,303 @@
+// SPDX-License-Identifier: MIT
+//! Scene import from heightmaps and image slice stacks
+//!
+//! Heightmaps are grayscale images where brightness is terrain height; each
+//! pixel becomes a column colored by a [`ColorRamp`] over absolute height.
+//! Only voxels visible from above or the sides are generated unless `solid`
+//! is set. Slice stacks are a directory of same-sized PNG images, one per Y
+//! layer in file name order; pixels at or above a brightness threshold
+//! become voxels with the pixel's color.
+//!
+//! Both importers check the result against the `[u16; 3]` voxel grid and
+//! the tier voxel limit before allocating voxels.
+
+use std::path::Path;
+
+use image::DynamicImage;
+use thiserror::Error;
+
+use super::scene::{Voxel, VoxelError, VoxelMetadata, VoxelScene};
+
+/// Largest grid extent along one axis (positions are `u16`)
+pub const MAX_GRID_EXTENT: u32 = u16::MAX as u32 + 1;
+
+/// Errors from building scenes out of images
+#[derive(Debug, Error)]
+pub enum VoxelImportError {
+    /// File could not be read
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Image could not be decoded
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+    /// Result would not fit the voxel grid
+    #[error("Scene of {0:?} voxels exceeds the {MAX_GRID_EXTENT} voxel grid limit")]
+    TooLarge([u32; 3]),
+    /// Slice does not match the size of the first slice
+    #[error("Slice {index} is {found:?}, expected {expected:?}")]
+    SliceSizeMismatch {
+        /// Slice index in file name order
+        index: usize,
+        /// Width and height of the first slice
+        expected: (u32, u32),
+        /// Width and height of this slice
+        found: (u32, u32),
+    },
+    /// Directory held no slice images
+    #[error("No .png slices found")]
+    NoSlices,
+    /// Result exceeds the tier voxel limit
+    #[error(transparent)]
+    Tier(#[from] VoxelError),
+}
+
+/// Colors sampled by normalized height, interpolated between stops
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorRamp {
+    /// `(position, sRGB color)` stops sorted by position in `0.0..=1.0`
+    pub stops: Vec<(f32, [u8; 4])>,
+}
+
+impl Default for ColorRamp {
+    /// Sand, grass, rock and snow
+    fn default() -> Self {
+        Self {
+            stops: vec![
+                (0.0, [194, 178, 128, 255]),
+                (0.3, [86, 125, 70, 255]),
+                (0.7, [120, 110, 100, 255]),
+                (1.0, [245, 245, 250, 255]),
+            ],
+        }
+    }
+}
+
+impl ColorRamp {
+    /// Color at `t` (clamped to the first and last stops)
+    pub fn sample(&self, t: f32) -> [u8; 4] {
+        let Some(&(first, first_color)) = self.stops.first() else {
+            return [255; 4];
+        };
+        if t <= first {
+            return first_color;
+        }
+        for pair in self.stops.windows(2) {
+            let ((a, from), (b, to)) = (pair[0], pair[1]);
+            if t <= b {
+                let f = if b > a { (t - a) / (b - a) } else { 1.0 };
+                return std::array::from_fn(|i| (from[i] as f32 + (to[i] as f32 - from[i] as f32) * f).round() as u8);
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
+}
+
+/// Options for [`import_heightmap`]
+#[derive(Debug, Clone)]
+pub struct HeightmapSettings {
+    /// Height in voxels of a white pixel
+    pub max_height: u32,
+    /// Colors by voxel height over `max_height`
+    pub ramp: ColorRamp,
+    /// Material ID of every voxel
+    pub material_id: u8,
+    /// Fill columns down to zero instead of only the visible shell
+    pub solid: bool,
+}
+
+impl Default for HeightmapSettings {
+    fn default() -> Self {
+        Self { max_height: 64, ramp: ColorRamp::default(), material_id: 0, solid: false }
+    }
+}
+
+/// Build terrain from a grayscale heightmap, image X and Y along scene X and Z
+pub fn import_heightmap(
+    image: &DynamicImage,
+    settings: &HeightmapSettings,
+    name: &str,
+) -> Result<VoxelScene, VoxelImportError> {
+    let (width, depth) = (image.width(), image.height());
+    let max_height = settings.max_height.max(1);
+    check_extent([width, max_height, depth])?;
+
+    let luma = image.to_luma16();
+    let top = max_height - 1;
+    let heights: Vec<u32> = luma.pixels().map(|p| (p.0[0] as u64 * top as u64 / u16::MAX as u64) as u32).collect();
+    let height_at = |x: i64, z: i64| -> Option<u32> {
+        (x >= 0 && z >= 0 && x < width as i64 && z < depth as i64).then(|| heights[(z * width as i64 + x) as usize])
+    };
+
+    // Lowest voxel of each column: down to the lowest neighbor, or the floor at the edges
+    let mut columns = Vec::with_capacity(heights.len());
+    let mut total = 0usize;
+    for z in 0..depth as i64 {
+        for x in 0..width as i64 {
+            let h = heights[(z * width as i64 + x) as usize];
+            let low = if settings.solid {
+                0
+            } else {
+                [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                    .iter()
+                    .map(|(dx, dz)| height_at(x + dx, z + dz).map_or(0, |n| (n + 1).min(h)))
+                    .min()
+                    .unwrap_or(0)
+            };
+            total += (h - low + 1) as usize;
+            columns.push(low..=h);
+        }
+    }
+    check_tier(total)?;
+
+    let mut voxels = Vec::with_capacity(total);
+    for (i, column) in columns.into_iter().enumerate() {
+        let (x, z) = (i as u32 % width, i as u32 / width);
+        for y in column {
+            voxels.push(Voxel {
+                position: [x as u16, y as u16, z as u16],
+                color: settings.ramp.sample(y as f32 / top.max(1) as f32),
+                material_id: settings.material_id,
+            });
+        }
+    }
+    Ok(build_scene(name, [width, max_height, depth], voxels))
+}
+
+/// Load a heightmap image from disk and build terrain from it
+pub fn import_heightmap_file(path: &Path, settings: &HeightmapSettings) -> Result<VoxelScene, VoxelImportError> {
+    let image = image::open(path)?;
+    import_heightmap(&image, settings, &file_name(path))
+}
+
+/// Build a scene from slice images, slice `i` becoming layer `y = i`
+///
+/// Image X and Y map to scene X and Z. Pixels whose brightness is at least
+/// `threshold` become opaque voxels with the pixel's color.
+pub fn import_image_stack(
+    slices: &[DynamicImage],
+    threshold: u8,
+    material_id: u8,
+    name: &str,
+) -> Result<VoxelScene, VoxelImportError> {
+    let first = slices.first().ok_or(VoxelImportError::NoSlices)?;
+    let expected = (first.width(), first.height());
+    for (index, slice) in slices.iter().enumerate() {
+        let found = (slice.width(), slice.height());
+        if found != expected {
+            return Err(VoxelImportError::SliceSizeMismatch { index, expected, found });
+        }
+    }
+    check_extent([expected.0, slices.len() as u32, expected.1])?;
+
+    let slices: Vec<_> = slices.iter().map(|s| (s.to_luma8(), s.to_rgb8())).collect();
+    let total = slices.iter().map(|(luma, _)| luma.pixels().filter(|p| p.0[0] >= threshold).count()).sum();
+    check_tier(total)?;
+
+    let mut voxels = Vec::with_capacity(total);
+    for (y, (luma, rgb)) in slices.iter().enumerate() {
+        for (x, z, pixel) in luma.enumerate_pixels() {
+            if pixel.0[0] >= threshold {
+                let [r, g, b] = rgb.get_pixel(x, z).0;
+                voxels.push(Voxel { position: [x as u16, y as u16, z as u16], color: [r, g, b, 255], material_id });
+            }
+        }
+    }
+    Ok(build_scene(name, [expected.0, slices.len() as u32, expected.1], voxels))
+}
+
+/// Load every `.png` in a directory, sorted by file name, as a slice stack
+pub fn import_image_stack_dir(dir: &Path, threshold: u8, material_id: u8) -> Result<VoxelScene, VoxelImportError> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("png")) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let slices = paths.iter().map(image::open).collect::<Result<Vec<_>, _>>()?;
+    import_image_stack(&slices, threshold, material_id, &file_name(dir))
+}
+
+fn check_extent(extent: [u32; 3]) -> Result<(), VoxelImportError> {
+    if extent.iter().any(|&e| e > MAX_GRID_EXTENT) {
+        return Err(VoxelImportError::TooLarge(extent));
+    }
+    Ok(())
+}
+
+fn check_tier(count: usize) -> Result<(), VoxelError> {
+    let limit = crate::tier::max_voxels();
+    if count > limit {
+        return Err(VoxelError::TierLimitReached { current: count, limit, tier: crate::tier::current_tier() });
+    }
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_stem().and_then(|s| s.to_str()).unwrap_or("imported").to_string()
+}
+
+fn build_scene(name: &str, dimensions: [u32; 3], voxels: Vec<Voxel>) -> VoxelScene {
+    VoxelScene::from_voxels(VoxelMetadata::new(name, (dimensions[0], dimensions[1], dimensions[2])), voxels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::prelude::default;
+    use image::{GrayImage, Luma, Rgb, RgbImage};
+
+    #[test]
+    fn test_heightmap_shell() {
+        // Flat ground with one white peak in the middle
+        let image = GrayImage::from_fn(3, 3, |x, y| Luma([if (x, y) == (1, 1) { 255 } else { 0 }]));
+        let settings = HeightmapSettings { max_height: 5, ..default() };
+        let scene = import_heightmap(&DynamicImage::ImageLuma8(image.clone()), &settings, "peak").unwrap();
+
+        // Eight ground voxels plus the four pillar voxels above the ground
+        assert_eq!(scene.voxel_count(), 12);
+        assert_eq!(scene.metadata.dimensions, (3, 5, 3));
+        let peak = scene.voxels().iter().find(|v| v.position == [1, 4, 1]).unwrap();
+        assert_eq!(peak.color, settings.ramp.sample(1.0));
+
+        let solid = HeightmapSettings { solid: true, ..settings };
+        assert_eq!(import_heightmap(&DynamicImage::ImageLuma8(image), &solid, "peak").unwrap().voxel_count(), 13);
+    }
+
+    #[test]
+    fn test_image_stack_threshold() {
+        let slice = |on: (u32, u32)| {
+            DynamicImage::ImageRgb8(RgbImage::from_fn(4, 2, |x, y| if (x, y) == on { Rgb([200, 50, 10]) } else { Rgb([5, 5, 5]) }))
+        };
+        let scene = import_image_stack(&[slice((0, 0)), slice((3, 1))], 40, 2, "stack").unwrap();
+
+        assert_eq!(scene.metadata.dimensions, (4, 2, 2));
+        let mut positions: Vec<_> = scene.voxels().iter().map(|v| v.position).collect();
+        positions.sort();
+        assert_eq!(positions, vec![[0, 0, 0], [3, 1, 1]]);
+        assert!(scene.voxels().iter().all(|v| v.color == [200, 50, 10, 255] && v.material_id == 2));
+
+        let mismatched = import_image_stack(&[slice((0, 0)), DynamicImage::new_luma8(1, 1)], 40, 0, "bad");
+        assert!(matches!(mismatched, Err(VoxelImportError::SliceSizeMismatch { index: 1, .. })));
+    }
+
+    #[test]
+    fn test_extent_limits() {
+        let wide = DynamicImage::new_luma8(MAX_GRID_EXTENT + 1, 1);
+        assert!(matches!(
+            import_heightmap(&wide, &HeightmapSettings::default(), "wide"),
+            Err(VoxelImportError::TooLarge(_))
+        ));
+        let tall = HeightmapSettings { max_height: MAX_GRID_EXTENT + 1, ..default() };
+        assert!(import_heightmap(&DynamicImage::new_luma8(1, 1), &tall, "tall").is_err());
+    }
+
+    #[test]
+    fn test_ramp_interpolates() {
+        let ramp = ColorRamp { stops: vec![(0.0, [0, 0, 0, 255]), (1.0, [200, 100, 50, 255])] };
+        assert_eq!(ramp.sample(0.5), [100, 50, 25, 255]);
+        assert_eq!(ramp.sample(2.0), [200, 100, 50, 255]);
+    }
+}
@@ -0,0 +1,341 @@
+// SPDX-License-Identifier: MIT
+//! Point lights for emissive voxels
+//!
+//! Emissive voxels (see [`super::VoxelMaterialRegistry::is_emissive`]) are
+//! grouped into grid clusters and each cluster becomes one `PointLight`
+//! child of the scene root. The total light count across scenes is capped by
+//! `HeartOnConfig::lighting.max_lights`, shared by the scenes that have
+//! emissive voxels; when a scene has more clusters than its share, the
+//! cluster grid is coarsened until it fits.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use super::dummy_renderer::VoxelSize;
+use super::scene::Voxel;
+use super::{VoxelMaterialRegistry, VoxelScene, VoxelsChanged};
+
+/// Initial cluster edge length in voxels
+const CLUSTER_SIZE: u32 = 8;
+
+/// Light intensity contributed by one emissive voxel (lumens)
+const LUMENS_PER_VOXEL: f32 = 800.0;
+
+/// Marks a point light spawned for emissive voxels of a scene
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxelLight {
+    /// Scene entity the light belongs to
+    pub parent: Entity,
+}
+
+/// A group of nearby emissive voxels lit by one point light
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmissiveCluster {
+    /// Centroid in scene grid space
+    pub center: Vec3,
+    /// Average linear color
+    pub color: Color,
+    /// Number of voxels in the cluster
+    pub voxel_count: usize,
+    /// Cluster edge length in voxels
+    pub size: u32,
+}
+
+/// Cluster emissive voxels into at most `max_clusters` groups
+///
+/// Clusters are ordered by voxel count (largest first), ties by position.
+pub fn cluster_emissive_voxels(
+    voxels: &[Voxel],
+    registry: &VoxelMaterialRegistry,
+    max_clusters: usize,
+) -> Vec<EmissiveCluster> {
+    let emissive: Vec<Voxel> = voxels.iter().filter(|v| registry.is_emissive(v)).copied().collect();
+    cluster_voxels(&emissive, max_clusters)
+}
+
+/// Cluster already filtered emissive voxels into at most `max_clusters` groups
+fn cluster_voxels(emissive: &[Voxel], max_clusters: usize) -> Vec<EmissiveCluster> {
+    if emissive.is_empty() || max_clusters == 0 {
+        return Vec::new();
+    }
+
+    let mut size = CLUSTER_SIZE;
+    loop {
+        let mut cells: HashMap<[u32; 3], (Vec3, Vec3, usize)> = HashMap::new();
+        for voxel in emissive {
+            let cell = voxel.position.map(|c| c as u32 / size);
+            let entry = cells.entry(cell).or_insert((Vec3::ZERO, Vec3::ZERO, 0));
+            entry.0 += Vec3::new(voxel.position[0] as f32, voxel.position[1] as f32, voxel.position[2] as f32);
+            let [r, g, b, _] = Color::rgba_u8(voxel.color[0], voxel.color[1], voxel.color[2], 255).as_linear_rgba_f32();
+            entry.1 += Vec3::new(r, g, b);
+            entry.2 += 1;
+        }
+
+        // Coarsen until the scene fits its light budget (positions are u16, so this ends at one cell)
+        if cells.len() > max_clusters {
+            size *= 2;
+            continue;
+        }
+
+        let mut clusters: Vec<([u32; 3], EmissiveCluster)> = cells
+            .into_iter()
+            .map(|(cell, (sum, color, count))| {
+                let n = count as f32;
+                let color = color / n;
+                let cluster = EmissiveCluster {
+                    center: sum / n,
+                    color: Color::rgb_linear(color.x, color.y, color.z),
+                    voxel_count: count,
+                    size,
+                };
+                (cell, cluster)
+            })
+            .collect();
+        clusters.sort_by(|a, b| b.1.voxel_count.cmp(&a.1.voxel_count).then(a.0.cmp(&b.0)));
+        clusters.truncate(max_clusters);
+        return clusters.into_iter().map(|(_, cluster)| cluster).collect();
+    }
+}
+
+/// Filter for scene entities whose lights need rebuilding
+type LightInputsChanged = Or<(Changed<Handle<VoxelScene>>, Changed<VoxelSize>)>;
+
+/// Emissive voxels of every scene that has any, by scene entity
+///
+/// Kept between frames so a budget change relights scenes without rescanning them.
+type EmissiveVoxels = HashMap<Entity, Vec<Voxel>>;
+
+/// Rebuild emissive lights when scenes, materials or the light budget change
+///
+/// `max_lights` is split across the scenes with emissive voxels; see
+/// [`light_budgets`]. Scenes without any never take a share, and the split
+/// is only redone when that set of scenes changes.
+pub fn sync_voxel_lights(
+    mut commands: Commands,
+    scenes: Res<Assets<VoxelScene>>,
+    registry: Res<VoxelMaterialRegistry>,
+    config: Res<crate::config::HeartOnConfig>,
+    changed_scenes: Query<Entity, LightInputsChanged>,
+    all_scenes: Query<(Entity, &Handle<VoxelScene>, Option<&VoxelSize>)>,
+    mut asset_events: EventReader<AssetEvent<VoxelScene>>,
+    mut voxel_edits: EventReader<VoxelsChanged>,
+    edit_tracker: Res<super::VoxelEditTracker>,
+    lights: Query<(Entity, &VoxelLight)>,
+    mut emissive: Local<EmissiveVoxels>,
+) {
+    let mut to_rebuild: HashSet<Entity> = changed_scenes.iter().collect();
+
+    // Edits are handled through VoxelsChanged in the frame they happen
+    for event in asset_events.read() {
+        if matches!(event, AssetEvent::Modified { id } if edit_tracker.is_edit(*id)) {
+            continue;
+        }
+        if let AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } = event {
+            to_rebuild.extend(all_scenes.iter().filter(|(_, h, _)| h.id() == *id).map(|(e, _, _)| e));
+        }
+    }
+    for change in voxel_edits.read() {
+        to_rebuild.extend(all_scenes.iter().filter(|(_, h, _)| h.id() == change.scene).map(|(e, _, _)| e));
+    }
+    // New rules can make any voxel emissive
+    if registry.is_changed() {
+        to_rebuild.extend(all_scenes.iter().map(|(entity, _, _)| entity));
+    }
+
+    // Lights of removed scenes
+    for (light, owner) in &lights {
+        if !all_scenes.contains(owner.parent) {
+            commands.entity(light).despawn_recursive();
+        }
+    }
+
+    // Rescan only the scenes that changed
+    let lit_before: HashSet<Entity> = emissive.keys().copied().collect();
+    emissive.retain(|entity, _| all_scenes.contains(*entity));
+    for &entity in &to_rebuild {
+        let voxels: Vec<Voxel> = all_scenes
+            .get(entity)
+            .ok()
+            .and_then(|(_, handle, _)| scenes.get(handle))
+            .map(|scene| scene.voxels().iter().filter(|v| registry.is_emissive(v)).copied().collect())
+            .unwrap_or_default();
+        if voxels.is_empty() {
+            emissive.remove(&entity);
+        } else {
+            emissive.insert(entity, voxels);
+        }
+    }
+
+    // The budget is shared, so a new total or a new set of lit scenes relights all of them
+    let lit: HashSet<Entity> = emissive.keys().copied().collect();
+    if config.is_changed() || lit != lit_before {
+        to_rebuild.extend(lit.iter().copied());
+    }
+    if to_rebuild.is_empty() {
+        return;
+    }
+
+    let budgets = light_budgets(config.lighting.max_lights as usize, lit.into_iter());
+
+    for (light, owner) in &lights {
+        if to_rebuild.contains(&owner.parent) {
+            commands.entity(light).despawn_recursive();
+        }
+    }
+
+    for entity in to_rebuild {
+        let Some(voxels) = emissive.get(&entity) else { continue };
+        let Ok((_, handle, size)) = all_scenes.get(entity) else { continue };
+        let Some(scene) = scenes.get(handle) else { continue };
+
+        let clusters = cluster_voxels(voxels, budgets.get(&entity).copied().unwrap_or(0));
+        if clusters.is_empty() {
+            continue;
+        }
+
+        let grid = scene.grid_transform(size);
+        let voxel_size = scene.voxel_size(size).0;
+        commands.entity(entity).with_children(|parent| {
+            for cluster in &clusters {
+                parent.spawn((
+                    PointLightBundle {
+                        point_light: PointLight {
+                            color: cluster.color,
+                            intensity: cluster.voxel_count as f32 * LUMENS_PER_VOXEL,
+                            range: cluster.size as f32 * voxel_size * 4.0,
+                            shadows_enabled: false,
+                            ..default()
+                        },
+                        transform: Transform::from_translation(grid.transform_point(cluster.center)),
+                        ..default()
+                    },
+                    VoxelLight { parent: entity },
+                ));
+            }
+        });
+        debug!("Spawned {} emissive lights for {}", clusters.len(), scene.metadata.name);
+    }
+}
+
+/// Even split of `max_lights` across lit scenes, with the remainder going to the first scenes by entity order
+///
+/// With more lit scenes than lights, the first `max_lights` scenes get one each.
+fn light_budgets(max_lights: usize, scenes: impl Iterator<Item = Entity>) -> HashMap<Entity, usize> {
+    let mut scenes: Vec<Entity> = scenes.collect();
+    scenes.sort();
+    let share = max_lights / scenes.len().max(1);
+    let extra = max_lights % scenes.len().max(1);
+    scenes.into_iter().enumerate().map(|(rank, entity)| (entity, share + usize::from(rank < extra))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::VoxelMaterialDef;
+
+    fn lava_registry() -> VoxelMaterialRegistry {
+        let mut registry = VoxelMaterialRegistry::default();
+        registry.insert(7, VoxelMaterialDef::default().with_emissive(true));
+        registry
+    }
+
+    fn lava(position: [u16; 3]) -> Voxel {
+        Voxel { position, color: [255, 80, 0, 255], material_id: 7 }
+    }
+
+    #[test]
+    fn test_light_budget_remainder_goes_to_first_scenes() {
+        let scenes: Vec<Entity> = (0..5).map(Entity::from_raw).collect();
+        let budgets = light_budgets(7, scenes.iter().rev().copied());
+        assert_eq!(scenes.iter().map(|e| budgets[e]).collect::<Vec<_>>(), [2, 2, 1, 1, 1]);
+
+        // More scenes than lights: every light is still handed out
+        let budgets = light_budgets(3, scenes.iter().copied());
+        assert_eq!(budgets.values().sum::<usize>(), 3);
+        assert_eq!(scenes.iter().map(|e| budgets[e]).collect::<Vec<_>>(), [1, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_nearby_voxels_share_a_light() {
+        let voxels = [lava([0, 0, 0]), lava([1, 0, 0]), lava([40, 0, 0])];
+        let clusters = cluster_emissive_voxels(&voxels, &lava_registry(), 10);
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].voxel_count, 2);
+        assert_eq!(clusters[0].center, Vec3::new(0.5, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_clusters_coarsen_to_fit_budget() {
+        let voxels: Vec<Voxel> = (0..16).map(|i| lava([i * 10, 0, 0])).collect();
+        let clusters = cluster_emissive_voxels(&voxels, &lava_registry(), 3);
+
+        assert!(clusters.len() <= 3);
+        assert_eq!(clusters.iter().map(|c| c.voxel_count).sum::<usize>(), 16);
+    }
+
+    #[test]
+    fn test_lights_spawned_under_scene_and_capped() {
+        let mut app = App::new();
+        app.add_plugins(bevy::asset::AssetPlugin::default())
+            .init_asset::<VoxelScene>()
+            .insert_resource(lava_registry())
+            .init_resource::<crate::config::HeartOnConfig>()
+            .init_resource::<crate::voxel::VoxelEditTracker>()
+            .add_event::<VoxelsChanged>()
+            .add_systems(Update, sync_voxel_lights);
+        app.world.resource_mut::<crate::config::HeartOnConfig>().lighting.max_lights = 2;
+
+        let mut scene = VoxelScene::test_cube(1);
+        for i in 0..5 {
+            scene.add_voxel(lava([i * 20, 10, 0])).unwrap();
+        }
+        let handle = app.world.resource_mut::<Assets<VoxelScene>>().add(scene);
+        let root = app.world.spawn(handle).id();
+        app.update();
+
+        let mut lights = app.world.query::<(&VoxelLight, &Parent, &PointLight)>();
+        assert_eq!(lights.iter(&app.world).count(), 2);
+        assert!(lights.iter(&app.world).all(|(light, parent, _)| light.parent == root && parent.get() == root));
+
+        app.world.despawn(root);
+        app.update();
+        assert_eq!(app.world.query::<&VoxelLight>().iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn test_budget_goes_to_scenes_with_emissive_voxels() {
+        let mut app = App::new();
+        app.add_plugins(bevy::asset::AssetPlugin::default())
+            .init_asset::<VoxelScene>()
+            .insert_resource(lava_registry())
+            .init_resource::<crate::config::HeartOnConfig>()
+            .init_resource::<crate::voxel::VoxelEditTracker>()
+            .add_event::<VoxelsChanged>()
+            .add_systems(Update, sync_voxel_lights);
+        app.world.resource_mut::<crate::config::HeartOnConfig>().lighting.max_lights = 1;
+
+        // Plain regions first in entity order, the lava region last
+        for _ in 0..3 {
+            let handle = app.world.resource_mut::<Assets<VoxelScene>>().add(VoxelScene::test_cube(2));
+            app.world.spawn(handle);
+        }
+        let mut lava_scene = VoxelScene::test_cube(1);
+        lava_scene.add_voxel(lava([0, 4, 0])).unwrap();
+        let handle = app.world.resource_mut::<Assets<VoxelScene>>().add(lava_scene);
+        let lit = app.world.spawn(handle).id();
+        app.update();
+
+        let mut lights = app.world.query::<(Entity, &VoxelLight)>();
+        let spawned: Vec<(Entity, VoxelLight)> = lights.iter(&app.world).map(|(e, l)| (e, *l)).collect();
+        assert_eq!(spawned.len(), 1);
+        assert_eq!(spawned[0].1.parent, lit);
+
+        // Another plain region doesn't touch the existing light
+        let handle = app.world.resource_mut::<Assets<VoxelScene>>().add(VoxelScene::test_cube(2));
+        app.world.spawn(handle);
+        app.update();
+        let after: Vec<Entity> = lights.iter(&app.world).map(|(e, _)| e).collect();
+        assert_eq!(after, [spawned[0].0]);
+    }
+}
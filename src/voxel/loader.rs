@@ -1,17 +1,71 @@
 // SPDX-License-Identifier: MIT
 //! `VoxelScene` asset loader for .hvox files
+//!
+//! Reads format version 1 (fixed header, 11 bytes per voxel) and version 2
+//! (sectioned and compressed, see [`super::hvox`]). Format errors are
+//! [`HvoxFormatError`]s carrying the byte offset and voxel index involved.
 
 use bevy::asset::{AssetLoader, AsyncReadExt, io::Reader, LoadContext};
 use bevy::prelude::*;
 use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
 use thiserror::Error;
 
-use super::scene::{VoxelScene, VoxelMetadata, VoxelData, CommunityVoxelData, Voxel};
+use super::scene::{VoxelScene, VoxelMetadata, VoxelData, Voxel};
 
 /// Asset loader for .hvox voxel scene files
 #[derive(Default)]
 pub struct VoxelSceneLoader;
 
+/// How inconsistent scene data is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VoxelValidation {
+    /// Reject duplicate positions, voxels outside the declared dimensions and count mismatches
+    Strict,
+    /// Repair them with a warning (last duplicate wins, dimensions grow to fit)
+    #[default]
+    Lenient,
+}
+
+/// What to do with scenes larger than the current tier allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TierLimitPolicy {
+    /// Fail the load
+    #[default]
+    Reject,
+    /// Keep the first voxels up to the limit
+    Truncate,
+}
+
+/// Settings for [`VoxelSceneLoader`], set per asset in `.meta` files or with `load_with_settings`
+///
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use hearton_public::voxel::{VoxelScene, VoxelSceneLoaderSettings};
+///
+/// fn load(asset_server: Res<AssetServer>) {
+///     let _scene: Handle<VoxelScene> = asset_server.load_with_settings(
+///         "castle.hvox",
+///         |settings: &mut VoxelSceneLoaderSettings| settings.voxel_size = Some(0.25),
+///     );
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VoxelSceneLoaderSettings {
+    /// Replace the origin stored in the file
+    pub origin: Option<[f32; 3]>,
+    /// World-space voxel edge length (1.0 when unset)
+    pub voxel_size: Option<f32>,
+    /// Material IDs to replace, `from -> to`
+    pub material_remap: HashMap<u8, u8>,
+    /// Validation mode
+    pub validation: VoxelValidation,
+    /// Tier limit handling
+    pub tier_limit: TierLimitPolicy,
+}
+
 /// Errors that can occur when loading voxel scenes
 #[derive(Error, Debug)]
 pub enum VoxelLoaderError {
@@ -21,43 +75,170 @@ pub enum VoxelLoaderError {
     
     /// Invalid file format
     #[error("Invalid .hvox format: {0}")]
-    InvalidFormat(String),
+    InvalidFormat(#[from] HvoxFormatError),
+    
+    /// Invalid MagicaVoxel file
+    #[error("Invalid .vox format: {0}")]
+    InvalidVox(String),
+    
+    /// Invalid `.hvox.ron` text
+    #[error("Invalid .hvox.ron format: {0}")]
+    InvalidRon(String),
+    
+    /// Loader settings that can't be applied
+    #[error("Invalid loader settings: {0}")]
+    InvalidSettings(String),
     
     /// Tier limit exceeded
     #[error("Tier limit exceeded: {0}")]
     TierLimit(#[from] super::scene::VoxelError),
 }
 
+/// Where and why a .hvox file failed to parse or validate
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{kind}{}{}", .offset.map(|o| format!(" at byte {}", o)).unwrap_or_default(), .voxel.map(|i| format!(" (voxel {})", i)).unwrap_or_default())]
+pub struct HvoxFormatError {
+    /// What went wrong
+    pub kind: HvoxErrorKind,
+    /// Byte offset in the file, when the problem has one
+    pub offset: Option<u64>,
+    /// Index of the offending voxel in file order
+    pub voxel: Option<usize>,
+}
+
+/// Kinds of .hvox format errors
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum HvoxErrorKind {
+    /// Input ended before the header was complete
+    #[error("file too small for header")]
+    TooSmall,
+    /// Missing "HVOX" magic bytes
+    #[error("invalid magic header (expected HVOX)")]
+    BadMagic,
+    /// Version this reader does not support
+    #[error("unsupported version {0}")]
+    UnsupportedVersion(u32),
+    /// Input ended inside a structure
+    #[error("unexpected end of data: need {needed} bytes, have {available}")]
+    Truncated {
+        /// Bytes the structure needs
+        needed: u64,
+        /// Bytes left in the input
+        available: u64,
+    },
+    /// Voxel outside the declared dimensions
+    #[error("voxel at {position:?} lies outside dimensions {dimensions:?}")]
+    OutOfBounds {
+        /// Voxel position
+        position: [u16; 3],
+        /// Declared scene dimensions
+        dimensions: (u32, u32, u32),
+    },
+    /// Second voxel at an occupied position
+    #[error("voxel at {position:?} duplicates voxel {first}")]
+    Duplicate {
+        /// Shared position
+        position: [u16; 3],
+        /// Index of the earlier voxel
+        first: usize,
+    },
+    /// Bytes after the end of the encoded scene
+    #[error("{0} bytes of trailing data")]
+    TrailingData(u64),
+    /// Section CRC does not match its bytes
+    #[error("checksum mismatch in section {0}")]
+    Checksum(String),
+    /// Any other structural problem
+    #[error("{0}")]
+    Malformed(String),
+}
+
+impl HvoxFormatError {
+    /// Error without a location
+    pub fn new(kind: HvoxErrorKind) -> Self {
+        Self { kind, offset: None, voxel: None }
+    }
+
+    /// Attach a byte offset
+    pub fn at(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Attach a voxel index
+    pub fn voxel(mut self, index: usize) -> Self {
+        self.voxel = Some(index);
+        self
+    }
+}
+
+impl From<HvoxErrorKind> for VoxelLoaderError {
+    fn from(kind: HvoxErrorKind) -> Self {
+        Self::InvalidFormat(HvoxFormatError::new(kind))
+    }
+}
+
+/// Reject positions outside `dimensions`
+pub(crate) fn check_bounds(position: [u16; 3], dimensions: (u32, u32, u32)) -> Result<(), HvoxErrorKind> {
+    let (width, height, depth) = dimensions;
+    let [x, y, z] = position.map(u32::from);
+    if x >= width || y >= height || z >= depth {
+        return Err(HvoxErrorKind::OutOfBounds { position, dimensions });
+    }
+    Ok(())
+}
+
+/// Check magic bytes and return the format version
+pub(crate) fn check_header(bytes: &[u8]) -> Result<u32, HvoxFormatError> {
+    if bytes.len() < 8 {
+        return Err(HvoxFormatError::new(HvoxErrorKind::TooSmall).at(bytes.len() as u64));
+    }
+    if &bytes[0..4] != format::MAGIC {
+        return Err(HvoxFormatError::new(HvoxErrorKind::BadMagic).at(0));
+    }
+    Ok(u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]))
+}
+
 /// .hvox file format constants
-mod format {
+pub(crate) mod format {
     /// Magic header bytes: "HVOX"
     pub const MAGIC: &[u8; 4] = b"HVOX";
-    /// Format version
+    /// Format version of the fixed-header layout
     pub const VERSION: u32 = 1;
     /// Header size in bytes
     pub const HEADER_SIZE: usize = 64;
+    /// Bytes per voxel record in version 1
+    pub const VOXEL_SIZE: usize = 11;
 }
 
 impl AssetLoader for VoxelSceneLoader {
     type Asset = VoxelScene;
-    type Settings = ();
+    type Settings = VoxelSceneLoaderSettings;
     type Error = VoxelLoaderError;
 
     fn load<'a>(
         &'a self,
         reader: &'a mut Reader,
-        _settings: &'a Self::Settings,
+        settings: &'a Self::Settings,
         _load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
         Box::pin(async move {
-            let mut bytes = Vec::new();
-            reader.read_to_end(&mut bytes).await?;
-            
-            // Parse .hvox format
-            let scene = parse_hvox(&bytes)?;
+            // Decode voxels as bytes arrive instead of buffering the whole file
+            let mut parser = super::hvox_stream::HvoxStreamParser::with_validation(settings.validation)
+                .with_tier_limit(settings.tier_limit);
+            let mut buffer = vec![0; super::hvox_stream::READ_BUFFER_SIZE];
+            loop {
+                let read = reader.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
+                }
+                parser.feed(&buffer[..read])?;
+            }
+            let scene = parser.finish()?;
             
-            // Validate tier limits
-            scene.validate_tier()?;
+            // Apply settings, validation and tier limits; strict parsing already checked the voxels
+            let checked = settings.validation == VoxelValidation::Strict;
+            let scene = apply_settings(scene, settings, checked)?;
             
             info!("Loaded voxel scene: {} ({} voxels)", 
                   scene.metadata.name, 
@@ -72,38 +253,148 @@ impl AssetLoader for VoxelSceneLoader {
     }
 }
 
-/// Parse .hvox binary format
-fn parse_hvox(bytes: &[u8]) -> Result<VoxelScene, VoxelLoaderError> {
-    if bytes.len() < format::HEADER_SIZE {
-        return Err(VoxelLoaderError::InvalidFormat(
-            "File too small for header".to_string()
-        ));
+/// Apply loader settings to a parsed scene
+pub(crate) fn apply_loader_settings(
+    scene: VoxelScene,
+    settings: &VoxelSceneLoaderSettings,
+) -> Result<VoxelScene, VoxelLoaderError> {
+    apply_settings(scene, settings, false)
+}
+
+/// Apply loader settings, skipping voxel validation when `checked` is set
+fn apply_settings(
+    mut scene: VoxelScene,
+    settings: &VoxelSceneLoaderSettings,
+    checked: bool,
+) -> Result<VoxelScene, VoxelLoaderError> {
+    if let Some(origin) = settings.origin {
+        scene.metadata.origin = Vec3::from(origin);
     }
-    
-    // Verify magic header
-    if &bytes[0..4] != format::MAGIC {
-        return Err(VoxelLoaderError::InvalidFormat(
-            "Invalid magic header (expected HVOX)".to_string()
-        ));
+    if let Some(voxel_size) = settings.voxel_size {
+        if !(voxel_size > 0.0 && voxel_size.is_finite()) {
+            return Err(VoxelLoaderError::InvalidSettings(format!("voxel size must be positive and finite, got {}", voxel_size)));
+        }
+        scene.metadata.voxel_size = voxel_size;
     }
-    
-    // Parse metadata from header
-    let metadata = parse_hvox_metadata(&bytes[0..format::HEADER_SIZE])?;
-    
-    // Parse voxel data
-    let voxel_data = parse_hvox_voxels(
-        &bytes[format::HEADER_SIZE..],
-        metadata.voxel_count
-    )?;
-    
-    Ok(VoxelScene {
-        metadata,
-        voxel_data: VoxelData::Community(CommunityVoxelData { voxels: voxel_data }),
-    })
+
+    let name = scene.metadata.name.clone();
+    let VoxelData::Community(data) = &mut scene.voxel_data else {
+        scene.validate_tier()?;
+        return Ok(scene);
+    };
+
+    if !settings.material_remap.is_empty() {
+        for voxel in Arc::make_mut(&mut data.voxels) {
+            if let Some(&to) = settings.material_remap.get(&voxel.material_id) {
+                voxel.material_id = to;
+            }
+        }
+    }
+
+    if !checked {
+        validate_voxels(&name, &mut scene.metadata, Arc::make_mut(&mut data.voxels), settings.validation)?;
+    }
+
+    let limit = crate::tier::max_voxels();
+    if data.voxels.len() > limit && settings.tier_limit == TierLimitPolicy::Truncate {
+        warn!("{}: truncating {} voxels to the {} tier limit ({})", name, data.voxels.len(), crate::tier::current_tier().name(), limit);
+        Arc::make_mut(&mut data.voxels).truncate(limit);
+        scene.metadata.voxel_count = limit;
+    }
+    scene.validate_tier()?;
+
+    Ok(scene)
+}
+
+/// Check voxels against the metadata, repairing problems in lenient mode
+fn validate_voxels(
+    name: &str,
+    metadata: &mut VoxelMetadata,
+    voxels: &mut Vec<Voxel>,
+    mode: VoxelValidation,
+) -> Result<(), VoxelLoaderError> {
+    let strict = mode == VoxelValidation::Strict;
+
+    if metadata.voxel_count != voxels.len() {
+        if strict {
+            return Err(HvoxErrorKind::Malformed(format!(
+                "{}: header says {} voxels, found {}",
+                name,
+                metadata.voxel_count,
+                voxels.len()
+            ))
+            .into());
+        }
+        metadata.voxel_count = voxels.len();
+    }
+
+    // Indices sorted by position, in file order within a position
+    let mut order: Vec<usize> = (0..voxels.len()).collect();
+    order.sort_by_key(|&i| voxels[i].position);
+    let groups = || order.chunk_by(|&a, &b| voxels[a].position == voxels[b].position).filter(|g| g.len() > 1);
+
+    if strict {
+        // Report the duplicate met first in file order
+        if let Some(group) = groups().min_by_key(|g| g[1]) {
+            let kind = HvoxErrorKind::Duplicate { position: voxels[group[0]].position, first: group[0] };
+            return Err(HvoxFormatError::new(kind).voxel(group[1]).into());
+        }
+    } else {
+        // Later voxels win, matching `VoxelScene::add_voxel`
+        let mut dropped: Vec<usize> = groups().flat_map(|g| g[..g.len() - 1].iter().copied()).collect();
+        if !dropped.is_empty() {
+            warn!("{}: dropping {} duplicate voxels", name, dropped.len());
+            dropped.sort_unstable();
+            let mut index = 0;
+            let mut next = dropped.iter().peekable();
+            voxels.retain(|_| {
+                let keep = next.next_if_eq(&&index).is_none();
+                index += 1;
+                keep
+            });
+            metadata.voxel_count = voxels.len();
+        }
+    }
+
+    if strict {
+        for (index, voxel) in voxels.iter().enumerate() {
+            check_bounds(voxel.position, metadata.dimensions)
+                .map_err(|kind| HvoxFormatError::new(kind).voxel(index))?;
+        }
+        return Ok(());
+    }
+    let (width, height, depth) = metadata.dimensions;
+    let extent = voxels.iter().fold([0u32; 3], |extent, v| {
+        [0, 1, 2].map(|axis| extent[axis].max(v.position[axis] as u32 + 1))
+    });
+    if extent[0] > width || extent[1] > height || extent[2] > depth {
+        warn!("{}: growing dimensions {:?} to fit voxels", name, metadata.dimensions);
+        metadata.dimensions = (width.max(extent[0]), height.max(extent[1]), depth.max(extent[2]));
+    }
+    Ok(())
+}
+
+/// Parse .hvox binary format (any supported version)
+pub fn parse_hvox(bytes: &[u8]) -> Result<VoxelScene, VoxelLoaderError> {
+    parse_hvox_with(bytes, VoxelValidation::Lenient)
+}
+
+/// Parse .hvox binary format, rejecting malformed scenes in strict mode
+///
+/// Strict mode fails on voxels outside the declared dimensions, duplicate
+/// positions and bytes past the end of the scene; lenient mode leaves them
+/// to [`VoxelSceneLoaderSettings`] validation to repair.
+pub fn parse_hvox_with(bytes: &[u8], validation: VoxelValidation) -> Result<VoxelScene, VoxelLoaderError> {
+    if check_header(bytes)? == super::hvox::VERSION {
+        return super::hvox::parse_v2(bytes, validation);
+    }
+    let mut parser = super::hvox_stream::HvoxStreamParser::with_validation(validation);
+    parser.feed(bytes)?;
+    parser.finish()
 }
 
 /// Parse metadata from header bytes
-fn parse_hvox_metadata(header: &[u8]) -> Result<VoxelMetadata, VoxelLoaderError> {
+pub(crate) fn parse_hvox_metadata(header: &[u8]) -> Result<VoxelMetadata, VoxelLoaderError> {
     // Header layout:
     // 0-3: Magic "HVOX"
     // 4-7: Version (u32 LE)
@@ -116,9 +407,7 @@ fn parse_hvox_metadata(header: &[u8]) -> Result<VoxelMetadata, VoxelLoaderError>
     
     let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
     if version != format::VERSION {
-        return Err(VoxelLoaderError::InvalidFormat(
-            format!("Unsupported version: {}", version)
-        ));
+        return Err(HvoxFormatError::new(HvoxErrorKind::UnsupportedVersion(version)).at(4).into());
     }
     
     let width = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
@@ -136,50 +425,29 @@ fn parse_hvox_metadata(header: &[u8]) -> Result<VoxelMetadata, VoxelLoaderError>
     let name = String::from_utf8_lossy(&name_bytes[..name_len]).to_string();
     
     Ok(VoxelMetadata {
-        name,
-        dimensions: (width, height, depth),
         voxel_count,
         origin: Vec3::new(origin_x, origin_y, origin_z),
+        ..VoxelMetadata::new(name, (width, height, depth))
     })
 }
 
-/// Parse voxel array from data bytes
-fn parse_hvox_voxels(data: &[u8], expected_count: usize) -> Result<Vec<Voxel>, VoxelLoaderError> {
+/// Decode one version 1 voxel record
+pub(crate) fn decode_voxel(record: &[u8]) -> Voxel {
     // Voxel layout: 11 bytes per voxel
     // 0-1: x position (u16 LE)
     // 2-3: y position (u16 LE)
     // 4-5: z position (u16 LE)
     // 6-9: RGBA color (4x u8)
     // 10: material_id (u8)
-    
-    const VOXEL_SIZE: usize = 11;
-    let expected_size = expected_count * VOXEL_SIZE;
-    
-    if data.len() < expected_size {
-        return Err(VoxelLoaderError::InvalidFormat(
-            format!("Insufficient voxel data: expected {} bytes, got {}", 
-                    expected_size, data.len())
-        ));
+    Voxel {
+        position: [
+            u16::from_le_bytes([record[0], record[1]]),
+            u16::from_le_bytes([record[2], record[3]]),
+            u16::from_le_bytes([record[4], record[5]]),
+        ],
+        color: [record[6], record[7], record[8], record[9]],
+        material_id: record[10],
     }
-    
-    let mut voxels = Vec::with_capacity(expected_count);
-    
-    for i in 0..expected_count {
-        let offset = i * VOXEL_SIZE;
-        let chunk = &data[offset..offset + VOXEL_SIZE];
-        
-        voxels.push(Voxel {
-            position: [
-                u16::from_le_bytes([chunk[0], chunk[1]]),
-                u16::from_le_bytes([chunk[2], chunk[3]]),
-                u16::from_le_bytes([chunk[4], chunk[5]]),
-            ],
-            color: [chunk[6], chunk[7], chunk[8], chunk[9]],
-            material_id: chunk[10],
-        });
-    }
-    
-    Ok(voxels)
 }
 
 #[cfg(test)]
@@ -263,10 +531,168 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_reads_both_versions() {
+        let scene = VoxelScene::test_cube(3);
+        for version in [1, 2] {
+            let bytes = scene.to_hvox_with_version(version).unwrap();
+            let parsed = parse_hvox(&bytes).unwrap();
+            assert_eq!(parsed.voxel_count(), 27, "version {}", version);
+            assert_eq!(parsed.metadata.name, scene.metadata.name);
+        }
+        assert!(scene.to_hvox_with_version(3).is_err());
+    }
+
+    #[test]
+    fn test_settings_override_and_remap() {
+        let voxels = [
+            Voxel { position: [1, 1, 1], color: [255; 4], material_id: 3 },
+            Voxel { position: [2, 1, 1], color: [255; 4], material_id: 4 },
+        ];
+        let scene = parse_hvox(&create_test_hvox("remap", &voxels)).unwrap();
+        let settings = VoxelSceneLoaderSettings {
+            origin: Some([1.0, 2.0, 3.0]),
+            voxel_size: Some(0.5),
+            material_remap: HashMap::from([(3, 9)]),
+            ..default()
+        };
+
+        let scene = apply_loader_settings(scene, &settings).unwrap();
+        assert_eq!(scene.metadata.origin, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(scene.voxel_size(None).0, 0.5);
+        assert_eq!(scene.voxels().iter().map(|v| v.material_id).collect::<Vec<_>>(), [9, 4]);
+
+        let zero = VoxelSceneLoaderSettings { voxel_size: Some(0.0), ..default() };
+        let error = apply_loader_settings(scene, &zero).unwrap_err();
+        assert!(matches!(error, VoxelLoaderError::InvalidSettings(_)), "{}", error);
+    }
+
+    #[test]
+    fn test_strict_rejects_what_lenient_repairs() {
+        let voxels = [
+            Voxel { position: [1, 1, 1], color: [1, 1, 1, 255], material_id: 0 },
+            Voxel { position: [1, 1, 1], color: [2, 2, 2, 255], material_id: 0 },
+            Voxel { position: [12, 0, 0], color: [3, 3, 3, 255], material_id: 0 },
+        ];
+        let bytes = create_test_hvox("sloppy", &voxels);
+
+        let strict = VoxelSceneLoaderSettings { validation: VoxelValidation::Strict, ..default() };
+        let error = format_error(apply_loader_settings(parse_hvox(&bytes).unwrap(), &strict));
+        assert_eq!(error.kind, HvoxErrorKind::Duplicate { position: [1, 1, 1], first: 0 });
+        assert_eq!(error.voxel, Some(1));
+
+        let scene = apply_loader_settings(parse_hvox(&bytes).unwrap(), &default()).unwrap();
+        assert_eq!(scene.voxel_count(), 2);
+        assert_eq!(scene.voxels()[0].color, [2, 2, 2, 255]);
+        assert_eq!(scene.metadata.dimensions, (13, 10, 10));
+    }
+
+    #[test]
+    fn test_settings_deserialize_from_meta_ron() {
+        let settings: VoxelSceneLoaderSettings = ron::from_str(
+            "(origin: Some((0.0, 4.0, 0.0)), voxel_size: None, material_remap: {1: 2}, validation: Strict, tier_limit: Truncate)",
+        )
+        .unwrap();
+        assert_eq!(settings.tier_limit, TierLimitPolicy::Truncate);
+        assert_eq!(settings.material_remap[&1], 2);
+    }
+
     #[test]
     fn test_file_too_small() {
         let bytes = vec![0u8; 10]; // Too small
         let result = parse_hvox(&bytes);
         assert!(result.is_err());
     }
+
+    fn format_error(result: Result<VoxelScene, VoxelLoaderError>) -> HvoxFormatError {
+        match result {
+            Err(VoxelLoaderError::InvalidFormat(error)) => error,
+            other => panic!("expected a format error, got {:?}", other.map(|s| s.voxel_count())),
+        }
+    }
+
+    #[test]
+    fn test_strict_parse_locates_errors() {
+        let voxel = |x, y| Voxel { position: [x, y, 0], color: [255; 4], material_id: 0 };
+
+        // Dimensions are 10³, so x = 12 is out of bounds
+        let bytes = create_test_hvox("bounds", &[voxel(1, 1), voxel(12, 0)]);
+        assert!(parse_hvox(&bytes).is_ok());
+        let error = format_error(parse_hvox_with(&bytes, VoxelValidation::Strict));
+        assert!(matches!(error.kind, HvoxErrorKind::OutOfBounds { position: [12, 0, 0], .. }));
+        assert_eq!((error.offset, error.voxel), (Some(75), Some(1)));
+
+        let bytes = create_test_hvox("dupes", &[voxel(1, 1), voxel(2, 2), voxel(1, 1)]);
+        let error = format_error(parse_hvox_with(&bytes, VoxelValidation::Strict));
+        assert_eq!(error.kind, HvoxErrorKind::Duplicate { position: [1, 1, 0], first: 0 });
+        assert_eq!((error.offset, error.voxel), (Some(86), Some(2)));
+
+        let mut bytes = create_test_hvox("trailing", &[voxel(1, 1)]);
+        bytes.extend_from_slice(b"junk");
+        assert!(parse_hvox(&bytes).is_ok());
+        let error = format_error(parse_hvox_with(&bytes, VoxelValidation::Strict));
+        assert_eq!((error.kind, error.offset), (HvoxErrorKind::TrailingData(4), Some(75)));
+
+        let error = format_error(parse_hvox(&bytes[..70]));
+        assert_eq!(error.kind, HvoxErrorKind::Truncated { needed: 11, available: 6 });
+        assert_eq!(error.to_string(), "unexpected end of data: need 11 bytes, have 6 at byte 64 (voxel 0)");
+    }
+
+    #[test]
+    fn test_fuzz_corpus_never_panics() {
+        let mut seeds = vec![
+            create_test_hvox("seed", &[Voxel { position: [1, 2, 3], color: [9; 4], material_id: 1 }]),
+            VoxelScene::test_cube(3).to_hvox_with_version(1).unwrap(),
+            VoxelScene::test_cube(3).to_hvox_with_version(2).unwrap(),
+            Vec::new(),
+            b"HVOX".to_vec(),
+            b"HVOX\x02\0\0\0\xff\xff\xff\xff\0\0\0\0".to_vec(),
+        ];
+        // Header claiming u32::MAX voxels
+        let mut huge = create_test_hvox("huge", &[]);
+        huge[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+        seeds.push(huge);
+        // A few hundred bytes of full-chunk runs declaring far fewer voxels
+        let coords: Vec<[u16; 3]> = (0..16).map(|i| [i, i, 0]).collect();
+        seeds.push(super::super::hvox::tests::full_chunks_file(100, &coords));
+
+        // xorshift keeps the corpus deterministic
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        let strict = VoxelSceneLoaderSettings { validation: VoxelValidation::Strict, ..default() };
+        for seed in &seeds {
+            for _ in 0..300 {
+                let mut bytes = seed.clone();
+                match next() % 4 {
+                    0 if !bytes.is_empty() => bytes.truncate(next() as usize % bytes.len()),
+                    1 => bytes.extend((0..next() % 32).map(|_| next() as u8)),
+                    _ => {
+                        for _ in 0..1 + next() % 8 {
+                            if let Some(len) = std::num::NonZeroUsize::new(bytes.len()) {
+                                bytes[next() as usize % len] ^= 1 << (next() % 8);
+                            }
+                        }
+                    }
+                }
+
+                for validation in [VoxelValidation::Lenient, VoxelValidation::Strict] {
+                    if let Ok(scene) = parse_hvox_with(&bytes, validation) {
+                        let _ = apply_loader_settings(scene, &strict);
+                    }
+                }
+                let mut parser = super::super::hvox_stream::HvoxStreamParser::new();
+                for piece in bytes.chunks(1 + next() as usize % 40) {
+                    if parser.feed(piece).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: MIT
+//! Distance-based level of detail for voxel chunks
+//!
+//! Chunks farther from the camera are remeshed from a downsampled grid where
+//! each cell covers `2^level` voxels per axis. Transition distances are
+//! multiplied by `MeshingConfig::lod_distance_scale`, so higher quality
+//! presets keep full detail farther out. A hysteresis band around each
+//! transition keeps chunks on the boundary from popping back and forth.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use super::greedy::{greedy_mesh_chunk, ChunkedVoxels};
+use super::material_registry::VoxelPass;
+use super::mesh_data::VoxelMeshData;
+use super::scene::Voxel;
+use super::{VoxelChunk, VoxelInstance};
+
+/// Coarsest LOD level (cells of `2^MAX_LOD` voxels per axis)
+pub const MAX_LOD: u8 = 2;
+
+/// Distance (world units, at scale 1.0) beyond which LOD `i + 1` is used
+pub const LOD_DISTANCES: [f32; MAX_LOD as usize] = [96.0, 192.0];
+
+/// Fraction of a transition distance a chunk must move past before switching
+pub const LOD_HYSTERESIS: f32 = 0.1;
+
+/// Pick the LOD level for a chunk at `distance` from the camera
+///
+/// `current` is the chunk's present level; it only changes once the distance
+/// leaves the hysteresis band around a transition.
+pub fn select_lod(current: u8, distance: f32, distance_scale: f32) -> u8 {
+    let distance = distance / distance_scale.max(f32::EPSILON);
+    let mut level = current.min(MAX_LOD);
+
+    while level < MAX_LOD && distance > LOD_DISTANCES[level as usize] * (1.0 + LOD_HYSTERESIS) {
+        level += 1;
+    }
+    while level > 0 && distance < LOD_DISTANCES[level as usize - 1] * (1.0 - LOD_HYSTERESIS) {
+        level -= 1;
+    }
+    level
+}
+
+/// Requested LOD level per chunk, and chunks whose level changed
+#[derive(Resource, Debug, Default)]
+pub struct VoxelLodState {
+    levels: HashMap<Entity, HashMap<IVec3, u8>>,
+    dirty: HashMap<Entity, HashSet<IVec3>>,
+}
+
+impl VoxelLodState {
+    /// Requested level of a chunk (0 until the LOD system has seen it)
+    pub fn level(&self, scene: Entity, coord: IVec3) -> u8 {
+        self.levels
+            .get(&scene)
+            .and_then(|levels| levels.get(&coord))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Requested levels for every chunk of a scene
+    pub(crate) fn scene_levels(&self, scene: Entity) -> HashMap<IVec3, u8> {
+        self.levels.get(&scene).cloned().unwrap_or_default()
+    }
+
+    /// Take the chunks that need remeshing at a new level
+    pub(crate) fn take_dirty(&mut self) -> HashMap<Entity, HashSet<IVec3>> {
+        std::mem::take(&mut self.dirty)
+    }
+}
+
+/// Mesh one render pass of a chunk at a LOD level
+///
+/// Level 0 is the regular greedy mesh. Coarser levels merge each block of
+/// `2^level` voxels into one cell colored by its first voxel; faces on the
+/// chunk border are always emitted so neighbors at other levels leave no gaps.
+pub fn mesh_chunk_lod(chunked: &ChunkedVoxels, coord: IVec3, level: u8, pass: VoxelPass) -> VoxelMeshData {
+    if level == 0 {
+        return chunked.mesh_chunk_pass(coord, pass);
+    }
+
+    let mut cells: HashMap<[u16; 3], Voxel> = HashMap::new();
+    for voxel in chunked.pass_voxels(coord, pass) {
+        let position = voxel.position.map(|c| c >> level);
+        cells.entry(position).or_insert(Voxel { position, ..voxel });
+    }
+    let mut coarse: Vec<Voxel> = cells.into_values().collect();
+    coarse.sort_by_key(|v| v.position);
+
+    let mut mesh = greedy_mesh_chunk(coord >> level as i32, &coarse, |_| false);
+
+    // Coarse cell c covers voxels c * s ..= c * s + s - 1
+    let cell = (1u32 << level) as f32;
+    let offset = (cell - 1.0) * 0.5;
+    for position in &mut mesh.positions {
+        *position = position.map(|p| p * cell + offset);
+    }
+    mesh
+}
+
+/// Choose LOD levels for chunks from camera distance
+///
+/// Chunks whose level changes are marked dirty for the chunk renderer, which
+/// remeshes them asynchronously. Also reports the rendered LOD distribution.
+pub fn update_chunk_lods(
+    mut state: ResMut<VoxelLodState>,
+    config: Res<crate::config::HeartOnConfig>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    chunks: Query<(&VoxelChunk, &VoxelInstance, &GlobalTransform)>,
+    scene_entities: Query<(), With<Handle<super::VoxelScene>>>,
+    mut metrics: ResMut<crate::metrics::PerformanceMetrics>,
+) {
+    state.levels.retain(|scene, _| scene_entities.contains(*scene));
+
+    let mut distribution = [0; MAX_LOD as usize + 1];
+    for (chunk, _, _) in &chunks {
+        distribution[chunk.lod.min(MAX_LOD) as usize] += 1;
+    }
+    metrics.lod_distribution = distribution;
+
+    let eyes: Vec<Vec3> = cameras
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .map(|(_, transform)| transform.translation())
+        .collect();
+    if eyes.is_empty() {
+        return;
+    }
+
+    let scale = config.meshing.lod_distance_scale;
+    let state = &mut *state;
+    for (chunk, instance, transform) in &chunks {
+        let aabb = super::culling::chunk_world_aabb(chunk.coord, transform);
+        let (min, max) = (Vec3::from(aabb.min()), Vec3::from(aabb.max()));
+        let distance = eyes
+            .iter()
+            .map(|eye| eye.clamp(min, max).distance(*eye))
+            .fold(f32::MAX, f32::min);
+
+        let levels = state.levels.entry(instance.parent).or_default();
+        let requested = levels.get(&chunk.coord).copied().unwrap_or(chunk.lod);
+        let level = select_lod(requested, distance, scale);
+
+        // Only new requests are queued; the chunk keeps its old level until remeshed
+        if level != requested {
+            state.dirty.entry(instance.parent).or_default().insert(chunk.coord);
+        }
+        levels.insert(chunk.coord, level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::VoxelScene;
+
+    #[test]
+    fn test_select_lod_hysteresis() {
+        assert_eq!(select_lod(0, 50.0, 1.0), 0);
+        assert_eq!(select_lod(0, 300.0, 1.0), 2);
+        // Inside the band around 96: keep the current level
+        assert_eq!(select_lod(0, 100.0, 1.0), 0);
+        assert_eq!(select_lod(1, 90.0, 1.0), 1);
+        assert_eq!(select_lod(1, 80.0, 1.0), 0);
+        // Higher quality scale pushes transitions out
+        assert_eq!(select_lod(0, 150.0, 2.0), 0);
+        assert_eq!(select_lod(0, 150.0, 0.5), 2);
+    }
+
+    #[test]
+    fn test_coarse_mesh_covers_same_bounds() {
+        let chunked = ChunkedVoxels::new(VoxelScene::test_cube(8).voxels());
+        let full = mesh_chunk_lod(&chunked, IVec3::ZERO, 0, VoxelPass::Opaque);
+        let coarse = mesh_chunk_lod(&chunked, IVec3::ZERO, 2, VoxelPass::Opaque);
+
+        let bounds = |mesh: &VoxelMeshData| {
+            mesh.positions.iter().fold((Vec3::MAX, Vec3::MIN), |(lo, hi), p| {
+                (lo.min(Vec3::from(*p)), hi.max(Vec3::from(*p)))
+            })
+        };
+        assert_eq!(bounds(&full), bounds(&coarse));
+        assert!(coarse.triangle_count() < full.triangle_count());
+    }
+}
@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: MIT
+//! Shared material and mesh handles for voxel rendering
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::material_registry::VoxelPass;
+
+/// Cache key: RGBA color, material ID and render pass
+pub type VoxelMaterialKey = ([u8; 4], u8, VoxelPass);
+
+/// Deduplicated `StandardMaterial`s and the shared unit cube mesh
+///
+/// Voxels with the same color, material ID and pass share one material handle
+/// across all scenes. Entries nobody else references are dropped by
+/// [`purge_voxel_material_cache`] once voxel instances are despawned.
+#[derive(Resource, Debug, Default)]
+pub struct VoxelMaterialCache {
+    materials: HashMap<VoxelMaterialKey, Handle<StandardMaterial>>,
+    cube_mesh: Option<Handle<Mesh>>,
+}
+
+impl VoxelMaterialCache {
+    /// Get or create the opaque material for a voxel color and material ID
+    pub fn material(
+        &mut self,
+        materials: &mut Assets<StandardMaterial>,
+        color: [u8; 4],
+        material_id: u8,
+    ) -> Handle<StandardMaterial> {
+        self.pass_material(materials, color, material_id, VoxelPass::Opaque)
+    }
+
+    /// Get or create the material for a voxel color and material ID in a render pass
+    pub fn pass_material(
+        &mut self,
+        materials: &mut Assets<StandardMaterial>,
+        color: [u8; 4],
+        material_id: u8,
+        pass: VoxelPass,
+    ) -> Handle<StandardMaterial> {
+        self.materials
+            .entry((color, material_id, pass))
+            .or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: Color::rgba_u8(color[0], color[1], color[2], color[3]),
+                    perceptual_roughness: 0.8,
+                    metallic: 0.0,
+                    alpha_mode: pass.alpha_mode(),
+                    ..default()
+                })
+            })
+            .clone()
+    }
+
+    /// Get or create the unit cube mesh shared by per-voxel instances
+    pub fn cube_mesh(&mut self, meshes: &mut Assets<Mesh>) -> Handle<Mesh> {
+        self.cube_mesh
+            .get_or_insert_with(|| meshes.add(Cuboid::new(1.0, 1.0, 1.0)))
+            .clone()
+    }
+
+    /// Number of cached materials
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    /// Whether no materials are cached
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    /// Drop materials that are only referenced by the cache
+    ///
+    /// Returns the number of entries freed.
+    pub fn purge_unused(&mut self) -> usize {
+        let before = self.materials.len();
+        self.materials.retain(|_, handle| match handle {
+            Handle::Strong(strong) => Arc::strong_count(strong) > 1,
+            Handle::Weak(_) => false,
+        });
+        before - self.materials.len()
+    }
+}
+
+/// Free cached materials after voxel instances were despawned
+pub fn purge_voxel_material_cache(
+    mut cache: ResMut<VoxelMaterialCache>,
+    mut removed: RemovedComponents<super::VoxelInstance>,
+) {
+    if removed.read().count() == 0 {
+        return;
+    }
+
+    let freed = cache.purge_unused();
+    if freed > 0 {
+        debug!("Freed {} unused voxel materials ({} cached)", freed, cache.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_materials_are_deduplicated() {
+        let mut materials = Assets::<StandardMaterial>::default();
+        let mut cache = VoxelMaterialCache::default();
+
+        let a = cache.material(&mut materials, [255, 0, 0, 255], 0);
+        let b = cache.material(&mut materials, [255, 0, 0, 255], 0);
+        let c = cache.material(&mut materials, [255, 0, 0, 255], 1);
+
+        let d = cache.pass_material(&mut materials, [255, 0, 0, 255], 0, VoxelPass::Blend);
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, d);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(materials.get(&d).unwrap().alpha_mode, AlphaMode::Blend);
+    }
+
+    #[test]
+    fn test_purge_keeps_referenced_materials() {
+        let mut materials = Assets::<StandardMaterial>::default();
+        let mut cache = VoxelMaterialCache::default();
+
+        let kept = cache.material(&mut materials, [0, 255, 0, 255], 0);
+        drop(cache.material(&mut materials, [0, 0, 255, 255], 0));
+
+        assert_eq!(cache.purge_unused(), 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.material(&mut materials, [0, 255, 0, 255], 0), kept);
+    }
+
+    #[test]
+    fn test_cube_mesh_is_shared() {
+        let mut meshes = Assets::<Mesh>::default();
+        let mut cache = VoxelMaterialCache::default();
+
+        assert_eq!(cache.cube_mesh(&mut meshes), cache.cube_mesh(&mut meshes));
+        assert_eq!(meshes.len(), 1);
+    }
+}
@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: MIT
+//! Per-material render settings for voxels
+//!
+//! Voxels reference materials by ID. The registry decides which render pass
+//! a voxel goes into; IDs without an entry are opaque unless their color has
+//! alpha below 255, in which case they are alpha blended. It also marks
+//! emissive voxels, either per material or by a brightness threshold.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::scene::Voxel;
+
+/// Render pass a voxel is meshed into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum VoxelPass {
+    /// Solid geometry, alpha ignored
+    #[default]
+    Opaque,
+    /// Alpha-tested cutout: fragments with alpha below `threshold / 255` are discarded
+    Mask(u8),
+    /// Alpha blended and depth-sorted after opaque geometry
+    Blend,
+}
+
+impl VoxelPass {
+    /// Bevy alpha mode for materials of this pass
+    pub fn alpha_mode(self) -> AlphaMode {
+        match self {
+            VoxelPass::Opaque => AlphaMode::Opaque,
+            VoxelPass::Mask(threshold) => AlphaMode::Mask(threshold as f32 / 255.0),
+            VoxelPass::Blend => AlphaMode::Blend,
+        }
+    }
+
+    /// Whether geometry in this pass hides faces of voxels behind it
+    pub fn is_opaque(self) -> bool {
+        self == VoxelPass::Opaque
+    }
+}
+
+/// Render settings for one material ID
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct VoxelMaterialDef {
+    /// Forced render pass (`None` picks from the voxel's alpha)
+    pub pass: Option<VoxelPass>,
+    /// Whether voxels of this material emit light
+    pub emissive: bool,
+}
+
+impl VoxelMaterialDef {
+    /// Force a render pass regardless of voxel alpha
+    pub fn with_pass(mut self, pass: VoxelPass) -> Self {
+        self.pass = Some(pass);
+        self
+    }
+
+    /// Mark the material as light-emitting
+    pub fn with_emissive(mut self, emissive: bool) -> Self {
+        self.emissive = emissive;
+        self
+    }
+}
+
+/// Material settings by material ID
+///
+/// Changing the registry rebuilds every voxel scene.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct VoxelMaterialRegistry {
+    materials: HashMap<u8, VoxelMaterialDef>,
+    emissive_threshold: Option<f32>,
+}
+
+impl VoxelMaterialRegistry {
+    /// Register or replace the settings for a material ID
+    pub fn insert(&mut self, material_id: u8, def: VoxelMaterialDef) {
+        self.materials.insert(material_id, def);
+    }
+
+    /// Settings for a material ID
+    pub fn get(&self, material_id: u8) -> VoxelMaterialDef {
+        self.materials.get(&material_id).copied().unwrap_or_default()
+    }
+
+    /// Treat any voxel whose color luminance (0..1) reaches `threshold` as emissive
+    pub fn set_emissive_threshold(&mut self, threshold: Option<f32>) {
+        self.emissive_threshold = threshold;
+    }
+
+    /// Whether a voxel emits light
+    pub fn is_emissive(&self, voxel: &Voxel) -> bool {
+        if self.get(voxel.material_id).emissive {
+            return true;
+        }
+        let [r, g, b, _] = voxel.color.map(|c| c as f32 / 255.0);
+        self.emissive_threshold
+            .is_some_and(|threshold| 0.2126 * r + 0.7152 * g + 0.0722 * b >= threshold)
+    }
+
+    /// Render pass for a voxel
+    pub fn pass(&self, voxel: &Voxel) -> VoxelPass {
+        match self.get(voxel.material_id).pass {
+            Some(pass) => pass,
+            None if voxel.color[3] < 255 => VoxelPass::Blend,
+            None => VoxelPass::Opaque,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pass_from_alpha_and_overrides() {
+        let mut registry = VoxelMaterialRegistry::default();
+        let glass = Voxel { position: [0, 0, 0], color: [200, 200, 255, 100], material_id: 1 };
+        let stone = Voxel { position: [0, 0, 0], color: [90, 90, 90, 255], material_id: 0 };
+
+        assert_eq!(registry.pass(&glass), VoxelPass::Blend);
+        assert_eq!(registry.pass(&stone), VoxelPass::Opaque);
+
+        registry.insert(1, VoxelMaterialDef::default().with_pass(VoxelPass::Mask(128)));
+        assert_eq!(registry.pass(&glass), VoxelPass::Mask(128));
+        assert_eq!(VoxelPass::Mask(128).alpha_mode(), AlphaMode::Mask(128.0 / 255.0));
+    }
+
+    #[test]
+    fn test_emissive_by_flag_or_threshold() {
+        let mut registry = VoxelMaterialRegistry::default();
+        let lava = Voxel { position: [0, 0, 0], color: [255, 90, 0, 255], material_id: 3 };
+        let lamp = Voxel { position: [0, 0, 0], color: [255, 255, 230, 255], material_id: 0 };
+
+        assert!(!registry.is_emissive(&lava));
+        assert!(!registry.is_emissive(&lamp));
+
+        registry.insert(3, VoxelMaterialDef::default().with_emissive(true));
+        registry.set_emissive_threshold(Some(0.9));
+        assert!(registry.is_emissive(&lava));
+        assert!(registry.is_emissive(&lamp));
+    }
+}
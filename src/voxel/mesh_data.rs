@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: MIT
+//! CPU-side triangle mesh buffers produced by the voxel meshers
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+
+/// Triangle mesh data with per-vertex normals and colors
+#[derive(Debug, Clone, Default)]
+pub struct VoxelMeshData {
+    /// Vertex positions in scene grid space
+    pub positions: Vec<[f32; 3]>,
+    /// Vertex normals (unit length)
+    pub normals: Vec<[f32; 3]>,
+    /// Linear RGBA vertex colors
+    pub colors: Vec<[f32; 4]>,
+    /// Triangle list indices (counter-clockwise winding)
+    pub indices: Vec<u32>,
+}
+
+impl VoxelMeshData {
+    /// Number of vertices in the mesh
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Number of triangles in the mesh
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Whether the mesh contains no triangles
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Convert into a Bevy `Mesh` with position, normal and color attributes
+    pub fn into_mesh(self) -> Mesh {
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+            .with_inserted_indices(Indices::U32(self.indices))
+    }
+}
+
+/// Convert an sRGB voxel color into the linear float color used by vertex attributes
+pub fn linear_color(color: [u8; 4]) -> [f32; 4] {
+    Color::rgba_u8(color[0], color[1], color[2], color[3]).as_linear_rgba_f32()
+}
@@ -4,6 +4,9 @@
 use bevy::prelude::*;
 use bevy::asset::Asset;
 use bevy::reflect::TypePath;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use thiserror::Error;
 
 /// Voxel scene asset that can be loaded from .hvox files
@@ -16,20 +19,149 @@ pub struct VoxelScene {
 }
 
 /// Metadata about the voxel scene
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VoxelMetadata {
     /// Scene name
     pub name: String,
     /// Grid dimensions (width, height, depth)
     pub dimensions: (u32, u32, u32),
     /// Total voxel count
+    #[serde(default)]
     pub voxel_count: usize,
     /// World origin position
+    #[serde(default)]
     pub origin: Vec3,
+    /// World-space edge length of one voxel (a `VoxelSize` on the entity overrides it)
+    #[serde(default = "default_voxel_size")]
+    pub voxel_size: f32,
+    /// Free-form typed properties (author, license, tags, tool, ...)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, MetadataValue>,
+    /// Named points in scene grid space (spawn points, region anchors, ...)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<VoxelMarker>,
+}
+
+fn default_voxel_size() -> f32 {
+    1.0
+}
+
+impl Default for VoxelMetadata {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            dimensions: (0, 0, 0),
+            voxel_count: 0,
+            origin: Vec3::ZERO,
+            voxel_size: default_voxel_size(),
+            properties: BTreeMap::new(),
+            markers: Vec::new(),
+        }
+    }
+}
+
+impl VoxelMetadata {
+    /// Metadata for a scene at the origin with unit voxels and no properties
+    pub fn new(name: impl Into<String>, dimensions: (u32, u32, u32)) -> Self {
+        Self { name: name.into(), dimensions, ..default() }
+    }
+
+    /// Property value by key
+    pub fn property(&self, key: &str) -> Option<&MetadataValue> {
+        self.properties.get(key)
+    }
+
+    /// Insert or replace a property
+    pub fn set_property(&mut self, key: impl Into<String>, value: impl Into<MetadataValue>) {
+        self.properties.insert(key.into(), value.into());
+    }
+
+    /// First marker with the given name
+    pub fn marker(&self, name: &str) -> Option<&VoxelMarker> {
+        self.markers.iter().find(|m| m.name == name)
+    }
+}
+
+/// Typed value of a scene metadata property
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MetadataValue {
+    /// Boolean flag
+    Bool(bool),
+    /// Signed integer
+    Int(i64),
+    /// Floating point number
+    Float(f64),
+    /// UTF-8 text
+    Text(String),
+    /// Vector, e.g. a size or direction
+    Vec3(Vec3),
+    /// List of strings, e.g. tags
+    List(Vec<String>),
+}
+
+impl MetadataValue {
+    /// Text value, if this is `Text`
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for MetadataValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for MetadataValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<f64> for MetadataValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<&str> for MetadataValue {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl From<String> for MetadataValue {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<Vec3> for MetadataValue {
+    fn from(value: Vec3) -> Self {
+        Self::Vec3(value)
+    }
+}
+
+impl From<Vec<String>> for MetadataValue {
+    fn from(value: Vec<String>) -> Self {
+        Self::List(value)
+    }
+}
+
+/// Named point in a scene
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoxelMarker {
+    /// Marker name, not necessarily unique
+    pub name: String,
+    /// Position in scene grid space
+    pub position: Vec3,
 }
 
 /// Voxel data storage (tier-dependent)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum VoxelData {
     /// Community Edition - simple array (10M limit)
     Community(CommunityVoxelData),
@@ -39,21 +171,24 @@ pub enum VoxelData {
 }
 
 /// Community Edition voxel storage
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommunityVoxelData {
     /// Voxel array (up to 10M)
-    pub voxels: Vec<Voxel>,
+    ///
+    /// Shared with in-flight mesh jobs; mutate through [`Arc::make_mut`],
+    /// which copies the array only while a job still holds it.
+    pub voxels: Arc<Vec<Voxel>>,
 }
 
 /// Professional Edition voxel storage (placeholder for Epic 7)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProfessionalVoxelData {
     /// SVDAG compressed data (to be implemented in Epic 7)
     pub compressed_data: Vec<u8>,
 }
 
 /// Individual voxel definition
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Voxel {
     /// Grid position (supports up to 65,536³ grid)
     pub position: [u16; 3],
@@ -83,10 +218,53 @@ pub enum VoxelError {
 }
 
 impl VoxelScene {
+    /// Community tier scene holding `voxels`, with `metadata.voxel_count` set to match
+    pub fn from_voxels(mut metadata: VoxelMetadata, voxels: Vec<Voxel>) -> Self {
+        metadata.voxel_count = voxels.len();
+        Self { metadata, voxel_data: VoxelData::Community(CommunityVoxelData { voxels: Arc::new(voxels) }) }
+    }
+
+    /// Gray test scene with a voxel at each of `positions`, sized to fit them
+    #[cfg(test)]
+    pub(crate) fn from_positions(positions: impl IntoIterator<Item = [u16; 3]>) -> Self {
+        let voxels: Vec<Voxel> = positions
+            .into_iter()
+            .map(|position| Voxel { position, color: [128, 128, 128, 255], material_id: 0 })
+            .collect();
+        let size = voxels.iter().fold([0u32; 3], |size, v| std::array::from_fn(|i| size[i].max(v.position[i] as u32 + 1)));
+        Self::from_voxels(VoxelMetadata::new("test_scene", (size[0], size[1], size[2])), voxels)
+    }
+
     /// Get total voxel count
     pub fn voxel_count(&self) -> usize {
         self.metadata.voxel_count
     }
+
+    /// Voxel size for an entity, preferring its `VoxelSize` component
+    pub fn voxel_size(&self, size: Option<&super::VoxelSize>) -> super::VoxelSize {
+        size.copied().unwrap_or(super::VoxelSize(self.metadata.voxel_size))
+    }
+
+    /// Transform from scene grid space into the local space of an entity holding this scene
+    pub fn grid_transform(&self, size: Option<&super::VoxelSize>) -> Transform {
+        self.voxel_size(size).grid_transform(self.metadata.origin)
+    }
+
+    /// Get the voxel array (empty for Professional data)
+    pub fn voxels(&self) -> &[Voxel] {
+        match &self.voxel_data {
+            VoxelData::Community(data) => &data.voxels,
+            VoxelData::Professional(_) => &[],
+        }
+    }
+
+    /// Shared handle to the voxel array, for reading it off the main thread without a copy
+    pub fn shared_voxels(&self) -> Arc<Vec<Voxel>> {
+        match &self.voxel_data {
+            VoxelData::Community(data) => data.voxels.clone(),
+            VoxelData::Professional(_) => default(),
+        }
+    }
     
     /// Validate that scene doesn't exceed tier limits
     pub fn validate_tier(&self) -> Result<(), VoxelError> {
@@ -106,12 +284,13 @@ impl VoxelScene {
         match &mut self.voxel_data {
             VoxelData::Community(data) => {
                 // Check if voxel already exists at this position
-                if let Some(idx) = data.voxels.iter().position(|v| v.position == voxel.position) {
+                let voxels = Arc::make_mut(&mut data.voxels);
+                if let Some(idx) = voxels.iter().position(|v| v.position == voxel.position) {
                     // Update existing
-                    data.voxels[idx] = voxel;
+                    voxels[idx] = voxel;
                 } else {
                     // Add new
-                    data.voxels.push(voxel);
+                    voxels.push(voxel);
                     self.metadata.voxel_count += 1;
                 }
                 Ok(())
@@ -125,7 +304,7 @@ impl VoxelScene {
         match &mut self.voxel_data {
             VoxelData::Community(data) => {
                 if let Some(idx) = data.voxels.iter().position(|v| v.position == position) {
-                    data.voxels.swap_remove(idx);
+                    Arc::make_mut(&mut data.voxels).swap_remove(idx);
                     self.metadata.voxel_count -= 1;
                     Ok(true)
                 } else {
@@ -136,8 +315,90 @@ impl VoxelScene {
         }
     }
 
-    /// Serialize to .hvox format
+    /// Set every position in `min..=max` (inclusive) to the given color and material
+    ///
+    /// Returns the number of voxels written. Fails without writing anything
+    /// if the new voxels would take the scene past the tier limit.
+    pub fn fill_region(
+        &mut self,
+        min: [u16; 3],
+        max: [u16; 3],
+        color: [u8; 4],
+        material_id: u8,
+    ) -> Result<usize, String> {
+        let in_region = |p: [u16; 3]| (0..3).all(|i| p[i] >= min[i] && p[i] <= max[i]);
+
+        match &mut self.voxel_data {
+            VoxelData::Community(data) => {
+                // Index existing voxels inside the region once instead of searching per position
+                let existing: std::collections::HashMap<[u16; 3], usize> = data
+                    .voxels
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, v)| in_region(v.position))
+                    .map(|(i, v)| (v.position, i))
+                    .collect();
+
+                let volume: u64 = (0..3).map(|i| (max[i] as u64 + 1).saturating_sub(min[i] as u64)).product();
+                let added = volume - existing.len() as u64;
+                let limit = crate::tier::max_voxels();
+                if self.metadata.voxel_count as u64 + added > limit as u64 {
+                    return Err(format!(
+                        "Filling {} new voxels would exceed the {} tier limit ({})",
+                        added,
+                        crate::tier::current_tier().name(),
+                        limit
+                    ));
+                }
+
+                let voxels = Arc::make_mut(&mut data.voxels);
+                let mut written = 0;
+                for x in min[0]..=max[0] {
+                    for y in min[1]..=max[1] {
+                        for z in min[2]..=max[2] {
+                            let voxel = Voxel { position: [x, y, z], color, material_id };
+                            match existing.get(&voxel.position) {
+                                Some(&idx) => voxels[idx] = voxel,
+                                None => {
+                                    voxels.push(voxel);
+                                    self.metadata.voxel_count += 1;
+                                }
+                            }
+                            written += 1;
+                        }
+                    }
+                }
+                Ok(written)
+            }
+            VoxelData::Professional(_) => Err("Cannot modify Professional tier voxel data".to_string()),
+        }
+    }
+
+    /// Serialize to .hvox format version 1
+    ///
+    /// Fails for scenes with properties or markers, which only version 2 stores.
     pub fn to_hvox(&self) -> Result<Vec<u8>, String> {
+        self.to_hvox_with_version(1)
+    }
+
+    /// Serialize to a specific .hvox format version (1 or 2)
+    ///
+    /// Version 2 is compressed, has no name length or voxel count limits, and
+    /// is the only version that stores properties and markers.
+    pub fn to_hvox_with_version(&self, version: u32) -> Result<Vec<u8>, String> {
+        match version {
+            1 => self.to_hvox_v1(),
+            super::hvox::VERSION => super::hvox::write_v2(self),
+            _ => Err(format!("Unsupported .hvox version: {}", version)),
+        }
+    }
+
+    fn to_hvox_v1(&self) -> Result<Vec<u8>, String> {
+        let voxel_count = u32::try_from(self.voxel_count())
+            .map_err(|_| "Too many voxels for .hvox v1, use version 2".to_string())?;
+        if !self.metadata.properties.is_empty() || !self.metadata.markers.is_empty() {
+            return Err(".hvox v1 cannot store properties or markers, use version 2".to_string());
+        }
         let mut bytes = Vec::new();
         
         // Magic header
@@ -152,7 +413,7 @@ impl VoxelScene {
         bytes.extend_from_slice(&self.metadata.dimensions.2.to_le_bytes());
         
         // Voxel count
-        bytes.extend_from_slice(&(self.voxel_count() as u32).to_le_bytes());
+        bytes.extend_from_slice(&voxel_count.to_le_bytes());
         
         // Origin
         bytes.extend_from_slice(&self.metadata.origin.x.to_le_bytes());
@@ -168,7 +429,7 @@ impl VoxelScene {
         // Voxel data
         match &self.voxel_data {
             VoxelData::Community(data) => {
-                for voxel in &data.voxels {
+                for voxel in data.voxels.iter() {
                     bytes.extend_from_slice(&voxel.position[0].to_le_bytes());
                     bytes.extend_from_slice(&voxel.position[1].to_le_bytes());
                     bytes.extend_from_slice(&voxel.position[2].to_le_bytes());
@@ -182,6 +443,29 @@ impl VoxelScene {
         Ok(bytes)
     }
     
+    /// Serialize to MagicaVoxel `.vox` format
+    ///
+    /// Colors are quantized to 255 palette entries when needed and scenes
+    /// wider than 256 voxels are split into several models.
+    pub fn to_vox(&self) -> Result<Vec<u8>, String> {
+        super::vox::write_vox(self)
+    }
+
+    /// Export as a binary glTF 2.0 mesh with vertex colors
+    pub fn to_glb(&self) -> Result<Vec<u8>, String> {
+        super::export::write_glb(&super::export::scene_mesh(self), &self.metadata.name)
+    }
+
+    /// Export as OBJ text plus the MTL file it references as `mtl_file`
+    pub fn to_obj(&self, mtl_file: &str) -> (String, String) {
+        super::export::write_obj(&super::export::scene_mesh(self), &self.metadata.name, mtl_file)
+    }
+
+    /// Serialize to human-readable `.hvox.ron`, as runs along +X when `rows` is set
+    pub fn to_hvox_ron(&self, rows: bool) -> Result<String, String> {
+        super::hvox_ron::write_ron(self, rows)
+    }
+
     /// Create a simple test scene
     pub fn test_cube(size: u16) -> Self {
         let mut voxels = Vec::new();
@@ -203,17 +487,9 @@ impl VoxelScene {
             }
         }
         
-        let voxel_count = voxels.len();
-        
-        Self {
-            metadata: VoxelMetadata {
-                name: format!("test_cube_{}x{}x{}", size, size, size),
-                dimensions: (size as u32, size as u32, size as u32),
-                voxel_count,
-                origin: Vec3::ZERO,
-            },
-            voxel_data: VoxelData::Community(CommunityVoxelData { voxels }),
-        }
+        let name = format!("test_cube_{}x{}x{}", size, size, size);
+        let size = size as u32;
+        Self::from_voxels(VoxelMetadata::new(name, (size, size, size)), voxels)
     }
 }
 
@@ -239,13 +515,11 @@ mod tests {
         // Create a scene with metadata claiming excessive voxels
         let scene = VoxelScene {
             metadata: VoxelMetadata {
-                name: "huge_scene".to_string(),
-                dimensions: (10000, 10000, 10000),
                 voxel_count: 50_000_000, // Exceeds Community 10M limit
-                origin: Vec3::ZERO,
+                ..VoxelMetadata::new("huge_scene", (10000, 10000, 10000))
             },
             voxel_data: VoxelData::Community(CommunityVoxelData {
-                voxels: Vec::new(),
+                voxels: default(),
             }),
         };
         
@@ -290,6 +564,27 @@ mod tests {
         assert_eq!(scene.voxel_count(), 1);
     }
 
+    #[test]
+    fn test_fill_region() {
+        let mut scene = VoxelScene::test_cube(2); // 8 voxels in 0..2
+        let written = scene.fill_region([1, 1, 1], [2, 2, 2], [9, 9, 9, 255], 3).unwrap();
+
+        assert_eq!(written, 8);
+        // 1 overlapping voxel updated, 7 new
+        assert_eq!(scene.voxel_count(), 15);
+        assert_eq!(scene.voxels().len(), 15);
+
+        let overlap = scene.voxels().iter().find(|v| v.position == [1, 1, 1]).unwrap();
+        assert_eq!(overlap.color, [9, 9, 9, 255]);
+        assert_eq!(overlap.material_id, 3);
+
+        // The whole grid is over every bounded tier limit and is rejected up front
+        if crate::tier::max_voxels() != usize::MAX {
+            assert!(scene.fill_region([0; 3], [u16::MAX; 3], [1; 4], 0).is_err());
+            assert_eq!(scene.voxel_count(), 15);
+        }
+    }
+
     #[test]
     fn test_serialization() {
         let scene = VoxelScene::test_cube(2); // 8 voxels
@@ -297,5 +592,11 @@ mod tests {
         
         assert_eq!(&bytes[0..4], b"HVOX");
         assert_eq!(bytes.len(), 64 + 8 * 11); // Header + 8 voxels * 11 bytes
+
+        // v1 would silently drop these
+        let mut tagged = scene.clone();
+        tagged.metadata.markers.push(VoxelMarker { name: "spawn".into(), position: Vec3::ZERO });
+        assert!(tagged.to_hvox().is_err());
+        assert!(tagged.to_hvox_with_version(2).is_ok());
     }
 }
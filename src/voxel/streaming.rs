@@ -0,0 +1,535 @@
+// SPDX-License-Identifier: MIT
+//! Region streaming around a focus entity
+//!
+//! Large worlds are split into cubic regions. Regions close to the
+//! [`StreamingFocus`] are loaded on the async compute pool and spawned as
+//! child entities holding their own `Handle<VoxelScene>`; distant regions are
+//! despawned again. Only resident regions are visible to the renderer,
+//! `PerformanceMetrics` and tier checks.
+//!
+//! Distances are measured in the world entity's local space, so a world can
+//! be moved by its `Transform` and scaled per voxel with [`super::VoxelSize`].
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::hvox_stream::HvoxView;
+use super::scene::{VoxelMetadata, VoxelScene};
+
+/// Marks the entity whose position drives streaming (usually the camera)
+#[derive(Component, Debug, Default)]
+pub struct StreamingFocus;
+
+/// Where a streamed world gets its voxels from
+#[derive(Debug, Clone)]
+pub enum StreamingSource {
+    /// A single large .hvox file, memory-mapped and indexed by region
+    HvoxFile(PathBuf),
+    /// A directory of pre-split region files named `region_{x}_{y}_{z}.hvox`
+    RegionDirectory(PathBuf),
+}
+
+/// A voxel world that is streamed in and out by region
+///
+/// The world itself holds no `Handle<VoxelScene>`; each resident region is a
+/// child entity with its own scene handle.
+#[derive(Component, Debug, Clone)]
+pub struct StreamedVoxelWorld {
+    /// Voxel source
+    pub source: StreamingSource,
+    /// Region edge length in voxels (used to split `HvoxFile` sources)
+    pub region_size: u16,
+    /// Regions closer than this (world-local units) are loaded
+    pub load_distance: f32,
+    /// Regions farther than this (world-local units) are unloaded
+    ///
+    /// Must be larger than `load_distance`; the gap prevents regions on the
+    /// boundary from thrashing.
+    pub unload_distance: f32,
+}
+
+impl StreamedVoxelWorld {
+    /// Create a streamed world with default region size and distances
+    pub fn new(source: StreamingSource) -> Self {
+        Self {
+            source,
+            region_size: 64,
+            load_distance: 128.0,
+            unload_distance: 160.0,
+        }
+    }
+
+    /// Set load and unload distances
+    pub fn with_distances(mut self, load: f32, unload: f32) -> Self {
+        self.load_distance = load;
+        self.unload_distance = unload.max(load);
+        self
+    }
+
+    /// Set the region edge length in voxels
+    pub fn with_region_size(mut self, size: u16) -> Self {
+        self.region_size = size.max(1);
+        self
+    }
+}
+
+/// Marker for a resident region entity spawned by the streamer
+#[derive(Component, Debug, Clone, Copy)]
+pub struct StreamedRegion {
+    /// Streamed world entity this region belongs to
+    pub world: Entity,
+    /// Region coordinate
+    pub coord: IVec3,
+    /// Voxels in this region
+    pub voxel_count: usize,
+}
+
+/// Voxel payload of one region
+#[derive(Debug, Clone)]
+enum RegionSource {
+    /// Byte ranges of the indexed file holding the region's voxels
+    Ranges(Arc<[Range<u64>]>),
+    /// Region file on disk
+    File(PathBuf),
+}
+
+/// All regions known for a streamed world
+#[derive(Debug, Default)]
+struct RegionIndex {
+    name: String,
+    origin: Vec3,
+    region_size: u16,
+    regions: HashMap<IVec3, RegionSource>,
+    /// Mapped file that `RegionSource::Ranges` point into
+    view: Option<HvoxView>,
+}
+
+impl RegionIndex {
+    /// Bounds of a region in the world entity's local space
+    fn region_bounds(&self, coord: IVec3, voxel_size: f32) -> (Vec3, Vec3) {
+        let size = self.region_size as f32;
+        // Voxels are centered on their grid position
+        let min = self.origin + (coord.as_vec3() * size - Vec3::splat(0.5)) * voxel_size;
+        (min, min + Vec3::splat(size * voxel_size))
+    }
+}
+
+/// Streaming bookkeeping attached to a [`StreamedVoxelWorld`]
+#[derive(Component, Default)]
+pub struct StreamingState {
+    index: Option<Arc<RegionIndex>>,
+    index_task: Option<Task<Result<RegionIndex, String>>>,
+    loading: HashMap<IVec3, Task<Result<VoxelScene, String>>>,
+    resident: HashMap<IVec3, (Entity, usize)>,
+    resident_voxels: usize,
+    /// Regions that failed to load, retried once the focus has moved past `unload_distance`
+    failed: HashSet<IVec3>,
+    /// Regions skipped for the tier limit, retried once the voxel total drops below `deferred_total`
+    deferred: HashSet<IVec3>,
+    deferred_total: usize,
+}
+
+impl StreamingState {
+    /// Number of resident regions
+    pub fn resident_regions(&self) -> usize {
+        self.resident.len()
+    }
+
+    /// Number of voxels in resident regions
+    pub fn resident_voxels(&self) -> usize {
+        self.resident_voxels
+    }
+
+    /// Number of regions currently being loaded
+    pub fn pending_regions(&self) -> usize {
+        self.loading.len()
+    }
+
+    /// Total number of regions in the world (0 until indexed)
+    pub fn total_regions(&self) -> usize {
+        self.index.as_ref().map_or(0, |index| index.regions.len())
+    }
+}
+
+/// What to do with a region given its distance to the focus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegionAction {
+    Load,
+    Unload,
+    Keep,
+}
+
+fn region_action(distance: f32, resident: bool, load_distance: f32, unload_distance: f32) -> RegionAction {
+    if !resident && distance <= load_distance {
+        RegionAction::Load
+    } else if resident && distance > unload_distance {
+        RegionAction::Unload
+    } else {
+        RegionAction::Keep
+    }
+}
+
+/// Distance from a point to an axis-aligned box (0 inside)
+fn distance_to_bounds(point: Vec3, (min, max): (Vec3, Vec3)) -> f32 {
+    point.distance(point.clamp(min, max))
+}
+
+/// Start indexing newly added streamed worlds
+pub fn init_streamed_worlds(
+    mut commands: Commands,
+    worlds: Query<(Entity, &StreamedVoxelWorld), Without<StreamingState>>,
+) {
+    for (entity, world) in &worlds {
+        let source = world.source.clone();
+        let region_size = world.region_size.max(1);
+        let task = AsyncComputeTaskPool::get().spawn(async move { build_index(&source, region_size) });
+
+        commands.entity(entity).insert(StreamingState {
+            index_task: Some(task),
+            ..default()
+        });
+    }
+}
+
+/// Streamed world entity with its placement in the scene
+type StreamedWorldItem<'a> = (
+    Entity,
+    &'a StreamedVoxelWorld,
+    &'a mut StreamingState,
+    Option<&'a GlobalTransform>,
+    Option<&'a super::VoxelSize>,
+);
+
+/// Load and unload regions based on distance to the [`StreamingFocus`]
+pub fn stream_regions(
+    mut commands: Commands,
+    mut scenes: ResMut<Assets<VoxelScene>>,
+    scene_handles: Query<&Handle<VoxelScene>>,
+    focus: Query<&GlobalTransform, With<StreamingFocus>>,
+    mut worlds: Query<StreamedWorldItem>,
+) {
+    let focus = focus.get_single().ok().map(GlobalTransform::translation);
+    // Every resident scene counts towards the tier limit, not just this world's regions
+    let mut total_voxels: usize = scene_handles.iter().filter_map(|h| scenes.get(h)).map(VoxelScene::voxel_count).sum();
+
+    for (world_entity, world, mut state, world_transform, size) in &mut worlds {
+        let size = size.copied().unwrap_or_default();
+        let state = &mut *state;
+        if total_voxels < state.deferred_total {
+            state.deferred.clear();
+            state.deferred_total = 0;
+        }
+
+        // Finish indexing
+        if let Some(task) = state.index_task.as_mut() {
+            let Some(result) = block_on(poll_once(task)) else { continue };
+            state.index_task = None;
+            match result {
+                Ok(index) => {
+                    info!("Indexed streamed world {}: {} regions", index.name, index.regions.len());
+                    state.index = Some(Arc::new(index));
+                }
+                Err(e) => {
+                    error!("Failed to index streamed world: {}", e);
+                    continue;
+                }
+            }
+        }
+
+        let Some(index) = state.index.clone() else { continue };
+
+        // Swap in finished region loads
+        let finished: Vec<IVec3> = state
+            .loading
+            .iter()
+            .filter(|(_, task)| task.is_finished())
+            .map(|(coord, _)| *coord)
+            .collect();
+
+        for coord in finished {
+            let Some(task) = state.loading.remove(&coord) else { continue };
+            let scene = match block_on(task) {
+                Ok(scene) => scene,
+                Err(e) => {
+                    error!("Failed to load region {:?}: {}", coord, e);
+                    state.failed.insert(coord);
+                    continue;
+                }
+            };
+
+            let voxel_count = scene.voxel_count();
+            let max_voxels = crate::tier::max_voxels();
+            if total_voxels.saturating_add(voxel_count) > max_voxels {
+                warn!(
+                    "Deferring region {:?}: {} resident voxels would exceed {} tier limit ({})",
+                    coord,
+                    total_voxels.saturating_add(voxel_count),
+                    crate::tier::current_tier().name(),
+                    max_voxels
+                );
+                state.deferred.insert(coord);
+                state.deferred_total = total_voxels;
+                continue;
+            }
+
+            let region = commands
+                .spawn((
+                    SpatialBundle::default(),
+                    scenes.add(scene),
+                    size,
+                    StreamedRegion { world: world_entity, coord, voxel_count },
+                    Name::new(format!("Region {} {} {}", coord.x, coord.y, coord.z)),
+                ))
+                .id();
+            commands.entity(world_entity).add_child(region);
+
+            state.resident.insert(coord, (region, voxel_count));
+            state.resident_voxels += voxel_count;
+            total_voxels += voxel_count;
+        }
+
+        let Some(focus) = focus else { continue };
+        let focus = match world_transform {
+            Some(transform) => transform.affine().inverse().transform_point3(focus),
+            None => focus,
+        };
+
+        for (&coord, source) in &index.regions {
+            let distance = distance_to_bounds(focus, index.region_bounds(coord, size.0));
+            if state.failed.contains(&coord) {
+                if distance > world.unload_distance {
+                    state.failed.remove(&coord);
+                }
+                continue;
+            }
+            if state.deferred.contains(&coord) {
+                continue;
+            }
+            let resident = state.resident.contains_key(&coord) || state.loading.contains_key(&coord);
+
+            match region_action(distance, resident, world.load_distance, world.unload_distance) {
+                RegionAction::Load => {
+                    let source = source.clone();
+                    let index = index.clone();
+                    let task = AsyncComputeTaskPool::get()
+                        .spawn(async move { load_region(&index, coord, &source) });
+                    state.loading.insert(coord, task);
+                }
+                RegionAction::Unload => {
+                    // Dropping an in-flight task cancels it
+                    state.loading.remove(&coord);
+                    if let Some((region, voxel_count)) = state.resident.remove(&coord) {
+                        commands.entity(region).despawn_recursive();
+                        state.resident_voxels -= voxel_count;
+                    }
+                }
+                RegionAction::Keep => {}
+            }
+        }
+
+    }
+}
+
+/// Build the region index for a source (runs on the async compute pool)
+fn build_index(source: &StreamingSource, region_size: u16) -> Result<RegionIndex, String> {
+    match source {
+        StreamingSource::HvoxFile(path) => {
+            // Not tier-validated: only resident regions count towards the limit
+            let view = HvoxView::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            index_regions(view, region_size).map_err(|e| format!("{}: {}", path.display(), e))
+        }
+        StreamingSource::RegionDirectory(dir) => {
+            let entries = std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+            let mut regions = HashMap::new();
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if let Some(coord) = parse_region_file_name(&path) {
+                    regions.insert(coord, RegionSource::File(path));
+                }
+            }
+            Ok(RegionIndex {
+                name: dir.display().to_string(),
+                origin: Vec3::ZERO,
+                region_size,
+                regions,
+                view: None,
+            })
+        }
+    }
+}
+
+/// Record which byte ranges of a mapped file hold each region's voxels
+fn index_regions(view: HvoxView, region_size: u16) -> Result<RegionIndex, super::loader::VoxelLoaderError> {
+    let mut ranges: HashMap<IVec3, Vec<Range<u64>>> = HashMap::new();
+    view.for_each_voxel_with_range(|voxel, range| {
+        let region = ranges.entry(region_coord(voxel.position, region_size)).or_default();
+        // Neighboring records and repeats of the same chunk merge into one range
+        match region.last_mut() {
+            Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+            _ => region.push(range),
+        }
+    })?;
+
+    Ok(RegionIndex {
+        name: view.metadata().name.clone(),
+        origin: view.metadata().origin,
+        region_size,
+        regions: ranges.into_iter().map(|(coord, ranges)| (coord, RegionSource::Ranges(ranges.into()))).collect(),
+        view: Some(view),
+    })
+}
+
+/// Materialize one region as a standalone scene (runs on the async compute pool)
+fn load_region(index: &RegionIndex, coord: IVec3, source: &RegionSource) -> Result<VoxelScene, String> {
+    match source {
+        RegionSource::Ranges(ranges) => {
+            let view = index.view.as_ref().ok_or("Region index has no mapped file")?;
+            let mut voxels = Vec::new();
+            for range in ranges.iter() {
+                // Chunks and merged ranges can hold voxels of neighboring regions
+                view.for_each_voxel_in(range.clone(), |voxel| {
+                    if region_coord(voxel.position, index.region_size) == coord {
+                        voxels.push(voxel);
+                    }
+                })
+                .map_err(|e| e.to_string())?;
+            }
+
+            // Voxels keep their world grid positions so regions line up; the grid spans up to the region's far corner
+            let end = (coord + 1).as_uvec3() * index.region_size as u32;
+            let name = format!("{}_region_{}_{}_{}", index.name, coord.x, coord.y, coord.z);
+            let metadata = VoxelMetadata { origin: index.origin, ..VoxelMetadata::new(name, (end.x, end.y, end.z)) };
+            Ok(VoxelScene::from_voxels(metadata, voxels))
+        }
+        RegionSource::File(path) => {
+            let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            super::loader::parse_hvox(&bytes).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Region containing a voxel position
+fn region_coord(position: [u16; 3], region_size: u16) -> IVec3 {
+    IVec3::new(
+        (position[0] / region_size) as i32,
+        (position[1] / region_size) as i32,
+        (position[2] / region_size) as i32,
+    )
+}
+
+/// Parse `region_{x}_{y}_{z}.hvox` into a region coordinate
+fn parse_region_file_name(path: &Path) -> Option<IVec3> {
+    if path.extension()? != "hvox" {
+        return None;
+    }
+    let stem = path.file_stem()?.to_str()?.strip_prefix("region_")?;
+    let mut parts = stem.split('_').map(str::parse::<i32>);
+    let coord = IVec3::new(parts.next()?.ok()?, parts.next()?.ok()?, parts.next()?.ok()?);
+    parts.next().is_none().then_some(coord)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_hysteresis() {
+        // Inside load distance: load
+        assert_eq!(region_action(50.0, false, 100.0, 150.0), RegionAction::Load);
+        // Between load and unload distance: keep whatever state we're in
+        assert_eq!(region_action(120.0, false, 100.0, 150.0), RegionAction::Keep);
+        assert_eq!(region_action(120.0, true, 100.0, 150.0), RegionAction::Keep);
+        // Beyond unload distance: unload
+        assert_eq!(region_action(200.0, true, 100.0, 150.0), RegionAction::Unload);
+        assert_eq!(region_action(200.0, false, 100.0, 150.0), RegionAction::Keep);
+    }
+
+    /// Index an 8³ cube saved in the given format version
+    fn cube_index(version: u32, region_size: u16) -> RegionIndex {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&VoxelScene::test_cube(8).to_hvox_with_version(version).unwrap()).unwrap();
+        index_regions(HvoxView::open(file.path()).unwrap(), region_size).unwrap()
+    }
+
+    #[test]
+    fn test_index_and_load_regions() {
+        for version in [1, 2] {
+            let index = cube_index(version, 4);
+            assert_eq!(index.regions.len(), 8);
+
+            let mut total = 0;
+            for (&coord, source) in &index.regions {
+                let scene = load_region(&index, coord, source).unwrap();
+                assert!(scene.voxels().iter().all(|v| region_coord(v.position, 4) == coord));
+                let (w, h, d) = scene.metadata.dimensions;
+                assert!(scene.voxels().iter().all(|v| {
+                    (v.position[0] as u32) < w && (v.position[1] as u32) < h && (v.position[2] as u32) < d
+                }));
+                total += scene.voxel_count();
+            }
+            assert_eq!(total, 512, "version {}", version);
+        }
+    }
+
+    #[test]
+    fn test_region_bounds_distance() {
+        let index = cube_index(1, 4);
+        let bounds = index.region_bounds(IVec3::new(1, 0, 0), 1.0);
+        assert_eq!(distance_to_bounds(Vec3::new(4.0, 1.0, 1.0), bounds), 0.0);
+        assert!((distance_to_bounds(Vec3::new(-0.5, 1.0, 1.0), bounds) - 4.0).abs() < 1e-5);
+
+        let scaled = index.region_bounds(IVec3::new(1, 0, 0), 0.5);
+        assert!((distance_to_bounds(Vec3::new(-0.25, 0.5, 0.5), scaled) - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_failed_region_retried_after_leaving_range() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("region_0_0_0.hvox"), b"not a voxel file").unwrap();
+
+        let mut app = App::new();
+        app.add_plugins((TaskPoolPlugin::default(), bevy::asset::AssetPlugin::default()))
+            .init_asset::<VoxelScene>()
+            .add_systems(Update, (init_streamed_worlds, stream_regions).chain());
+        let focus = app.world.spawn((StreamingFocus, GlobalTransform::default())).id();
+        let source = StreamingSource::RegionDirectory(dir.path().to_path_buf());
+        let world = app.world.spawn(StreamedVoxelWorld::new(source).with_distances(10.0, 20.0)).id();
+
+        let failed = |app: &App| app.world.get::<StreamingState>(world).unwrap().failed.contains(&IVec3::ZERO);
+        for _ in 0..200 {
+            app.update();
+            if failed(&app) {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert!(failed(&app));
+
+        // Still in range: no retry
+        app.update();
+        assert!(failed(&app));
+        assert_eq!(app.world.get::<StreamingState>(world).unwrap().pending_regions(), 0);
+
+        // Leaving the unload distance clears the failure, coming back retries
+        *app.world.get_mut::<GlobalTransform>(focus).unwrap() = GlobalTransform::from_xyz(1000.0, 0.0, 0.0);
+        app.update();
+        assert!(!failed(&app));
+        *app.world.get_mut::<GlobalTransform>(focus).unwrap() = GlobalTransform::default();
+        app.update();
+        assert_eq!(app.world.get::<StreamingState>(world).unwrap().pending_regions(), 1);
+    }
+
+    #[test]
+    fn test_parse_region_file_name() {
+        assert_eq!(parse_region_file_name(Path::new("w/region_1_-2_3.hvox")), Some(IVec3::new(1, -2, 3)));
+        assert_eq!(parse_region_file_name(Path::new("w/region_1_2.hvox")), None);
+        assert_eq!(parse_region_file_name(Path::new("w/region_1_2_3_4.hvox")), None);
+        assert_eq!(parse_region_file_name(Path::new("w/region_1_2_3.ron")), None);
+        assert_eq!(parse_region_file_name(Path::new("w/other_1_2_3.hvox")), None);
+    }
+}
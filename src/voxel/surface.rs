@@ -0,0 +1,212 @@
+// SPDX-License-Identifier: MIT
+//! Smooth surface extraction (naive surface nets)
+//!
+//! Voxel centers are treated as samples of a binary density field. Every
+//! cell whose eight corner samples straddle the surface gets one vertex,
+//! placed at the centroid of its crossing edges, and every solid/empty
+//! sample pair emits a quad joining the four cells around that edge.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::mesh_data::{linear_color, VoxelMeshData};
+use super::scene::VoxelScene;
+
+/// How a voxel scene entity is turned into geometry
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VoxelMeshMode {
+    /// One cube per voxel
+    #[default]
+    Blocky,
+    /// Smooth triangle mesh extracted with surface nets (the dummy fallback renderer draws cubes)
+    Smooth,
+}
+
+/// Corner offsets of a cell, indexed by bit (x = bit 0, y = bit 1, z = bit 2)
+const CORNERS: [IVec3; 8] = [
+    IVec3::new(0, 0, 0),
+    IVec3::new(1, 0, 0),
+    IVec3::new(0, 1, 0),
+    IVec3::new(1, 1, 0),
+    IVec3::new(0, 0, 1),
+    IVec3::new(1, 0, 1),
+    IVec3::new(0, 1, 1),
+    IVec3::new(1, 1, 1),
+];
+
+/// The 12 cell edges as pairs of corner indices
+const EDGES: [(usize, usize); 12] = [
+    (0, 1), (2, 3), (4, 5), (6, 7),
+    (0, 2), (1, 3), (4, 6), (5, 7),
+    (0, 4), (1, 5), (2, 6), (3, 7),
+];
+
+/// Extract a smooth surface from a voxel scene
+///
+/// Vertex positions are in grid space, matching the cube centers used by the
+/// blocky renderer. Colors are averaged from the solid voxels around each vertex.
+pub fn surface_nets(scene: &VoxelScene) -> VoxelMeshData {
+    let solid: HashMap<IVec3, [u8; 4]> = scene
+        .voxels()
+        .iter()
+        .map(|v| (grid_pos(v.position), v.color))
+        .collect();
+
+    let mut mesh = VoxelMeshData::default();
+    if solid.is_empty() {
+        return mesh;
+    }
+
+    // Candidate cells: every cell that has at least one solid corner.
+    // Sorted so vertex order is deterministic across runs.
+    let mut cells: Vec<IVec3> = solid
+        .keys()
+        .flat_map(|&p| CORNERS.iter().map(move |&o| p - o))
+        .collect();
+    cells.sort_unstable_by_key(|c| (c.z, c.y, c.x));
+    cells.dedup();
+
+    let mut cell_vertex: HashMap<IVec3, u32> = HashMap::new();
+
+    for cell in cells {
+        let mut mask = 0u8;
+        let mut color_sum = Vec4::ZERO;
+        for (i, &offset) in CORNERS.iter().enumerate() {
+            if let Some(color) = solid.get(&(cell + offset)) {
+                mask |= 1 << i;
+                color_sum += Vec4::from(linear_color(*color));
+            }
+        }
+
+        // Fully inside or outside: no surface crosses this cell
+        if mask == 0 || mask == 0xFF {
+            continue;
+        }
+
+        // Binary density: every crossing sits at its edge midpoint
+        let mut crossing_sum = Vec3::ZERO;
+        let mut crossings = 0.0;
+        for &(a, b) in &EDGES {
+            if (mask >> a) & 1 != (mask >> b) & 1 {
+                crossing_sum += (CORNERS[a] + CORNERS[b]).as_vec3() * 0.5;
+                crossings += 1.0;
+            }
+        }
+
+        // Outward normal points from solid corners towards empty ones
+        let mut gradient = Vec3::ZERO;
+        for (i, &offset) in CORNERS.iter().enumerate() {
+            let dir = offset.as_vec3() - Vec3::splat(0.5);
+            if (mask >> i) & 1 == 1 {
+                gradient -= dir;
+            } else {
+                gradient += dir;
+            }
+        }
+        let normal = gradient.try_normalize().unwrap_or(Vec3::Y);
+
+        let solid_corners = mask.count_ones() as f32;
+        let position = cell.as_vec3() + crossing_sum / crossings;
+
+        cell_vertex.insert(cell, mesh.positions.len() as u32);
+        mesh.positions.push(position.to_array());
+        mesh.normals.push(normal.to_array());
+        mesh.colors.push((color_sum / solid_corners).to_array());
+    }
+
+    // Emit one quad per solid/empty sample pair
+    let mut solid_positions: Vec<IVec3> = solid.keys().copied().collect();
+    solid_positions.sort_unstable_by_key(|p| (p.z, p.y, p.x));
+
+    for p in solid_positions {
+        for axis in 0..3 {
+            let step = IVec3::AXES[axis];
+            let u = IVec3::AXES[(axis + 1) % 3];
+            let v = IVec3::AXES[(axis + 2) % 3];
+
+            for (neighbor, solid_is_low) in [(p + step, true), (p - step, false)] {
+                if solid.contains_key(&neighbor) {
+                    continue;
+                }
+
+                // Lower sample of the crossing edge
+                let s = if solid_is_low { p } else { neighbor };
+                let quad = [s - u - v, s - v, s, s - u].map(|c| cell_vertex[&c]);
+
+                // Counter-clockwise around +axis when the solid sample is below
+                if solid_is_low {
+                    mesh.indices.extend_from_slice(&[quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+                } else {
+                    mesh.indices.extend_from_slice(&[quad[0], quad[2], quad[1], quad[0], quad[3], quad[2]]);
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+fn grid_pos(position: [u16; 3]) -> IVec3 {
+    IVec3::new(position[0] as i32, position[1] as i32, position[2] as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_scene_produces_no_geometry() {
+        let mesh = surface_nets(&VoxelScene::from_positions([]));
+        assert!(mesh.is_empty());
+        assert_eq!(mesh.vertex_count(), 0);
+    }
+
+    #[test]
+    fn test_single_voxel_snapshot() {
+        let mesh = surface_nets(&VoxelScene::from_positions([[1, 1, 1]]));
+        // 8 surrounding cells, 6 crossing edges -> 6 quads
+        assert_eq!(mesh.vertex_count(), 8);
+        assert_eq!(mesh.indices.len(), 36);
+    }
+
+    #[test]
+    fn test_cube_snapshot() {
+        let mesh = surface_nets(&VoxelScene::test_cube(2));
+        // 27 cells around the cube minus the fully solid center cell
+        assert_eq!(mesh.vertex_count(), 26);
+        // 4 crossing edges per face, 6 faces
+        assert_eq!(mesh.triangle_count(), 48);
+        assert_eq!(mesh.normals.len(), mesh.vertex_count());
+        assert_eq!(mesh.colors.len(), mesh.vertex_count());
+    }
+
+    #[test]
+    fn test_normals_point_outward() {
+        let mesh = surface_nets(&VoxelScene::from_positions([[1, 1, 1]]));
+        let center = Vec3::ONE;
+        for (position, normal) in mesh.positions.iter().zip(&mesh.normals) {
+            let outward = Vec3::from(*position) - center;
+            assert!(outward.dot(Vec3::from(*normal)) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_triangle_winding_matches_normals() {
+        let mesh = surface_nets(&VoxelScene::test_cube(3));
+        for tri in mesh.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(mesh.positions[tri[i] as usize]));
+            let face_normal = (b - a).cross(c - a);
+            let vertex_normal = Vec3::from(mesh.normals[tri[0] as usize]);
+            assert!(face_normal.dot(vertex_normal) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_into_mesh_preserves_counts() {
+        let data = surface_nets(&VoxelScene::test_cube(2));
+        let index_count = data.indices.len();
+        let mesh = data.into_mesh();
+        assert_eq!(mesh.count_vertices(), 26);
+        assert_eq!(mesh.indices().map(|i| i.len()), Some(index_count));
+    }
+}
@@ -0,0 +1,882 @@
+// SPDX-License-Identifier: MIT
+//! MagicaVoxel `.vox` import and export
+//!
+//! The main asset merges every visible model of the scene graph (`nTRN`,
+//! `nGRP` and `nSHP` nodes) into one [`VoxelScene`]; each model is also
+//! available untransformed as a labeled sub-asset `Model<index>`.
+//!
+//! MagicaVoxel is Z-up, so positions map to `(x, z, -y)`. The merged scene is
+//! shifted to start at the grid origin and `metadata.origin` holds the shift,
+//! keeping the MagicaVoxel world origin at the scene root. Voxel colors come
+//! from the `RGBA` chunk (or the default palette) and `material_id` is the
+//! palette index.
+//!
+//! [`write_vox`] (behind [`VoxelScene::to_vox`]) does the reverse: colors are
+//! reduced to a 255-entry palette with median cut when needed, and scenes
+//! wider than 256 voxels are split into several models placed by `nTRN`
+//! nodes. Material IDs are not exported.
+
+use bevy::asset::{AssetLoader, AsyncReadExt, io::Reader, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use std::collections::{HashMap, HashSet};
+
+use super::loader::{apply_loader_settings, TierLimitPolicy, VoxelLoaderError, VoxelSceneLoaderSettings};
+use super::scene::{Voxel, VoxelData, VoxelError, VoxelMetadata, VoxelScene};
+
+/// Largest `_t` translation component accepted
+///
+/// With models at most 256 wide and the scene graph at most 256 deep, placed
+/// positions and their extent stay far from `i32` overflow.
+const MAX_TRANSLATION: i32 = 1 << 20;
+
+/// Scene graph nesting limit (also catches cycles)
+const MAX_GRAPH_DEPTH: usize = 256;
+
+/// Asset loader for MagicaVoxel .vox files
+#[derive(Default)]
+pub struct VoxLoader;
+
+impl AssetLoader for VoxLoader {
+    type Asset = VoxelScene;
+    type Settings = VoxelSceneLoaderSettings;
+    type Error = VoxelLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let name = load_context
+                .path()
+                .file_stem()
+                .map_or_else(|| "vox".to_string(), |stem| stem.to_string_lossy().into_owned());
+            let vox = parse_vox(&bytes)?;
+
+            for index in 0..vox.models.len() {
+                let model = vox.model_scene(index, &format!("{}_model{}", name, index));
+                load_context.add_labeled_asset(format!("Model{}", index), apply_loader_settings(model, settings)?);
+            }
+            let scene = apply_loader_settings(vox.scene_with(&name, settings.tier_limit)?, settings)?;
+
+            info!(
+                "Loaded MagicaVoxel scene: {} ({} models, {} voxels)",
+                scene.metadata.name,
+                vox.models.len(),
+                scene.voxel_count()
+            );
+            Ok(scene)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["vox"]
+    }
+}
+
+/// One `SIZE` + `XYZI` model
+#[derive(Debug, Clone, Default)]
+pub struct VoxModel {
+    /// Model size in voxels (x, y, z; Z-up)
+    pub size: [u32; 3],
+    /// Voxels as `[x, y, z, palette index]`
+    pub voxels: Vec<[u8; 4]>,
+}
+
+/// Scene graph node
+#[derive(Debug, Clone)]
+enum VoxNode {
+    Transform { child: i32, layer: i32, rotation: u8, translation: IVec3, hidden: bool },
+    Group { children: Vec<i32>, hidden: bool },
+    Shape { models: Vec<i32> },
+}
+
+/// A parsed `.vox` file
+#[derive(Debug, Clone)]
+pub struct VoxFile {
+    /// Models in file order (untransformed, Z-up)
+    pub models: Vec<VoxModel>,
+    /// Colors by palette index (index 0 is unused)
+    pub palette: [[u8; 4]; 256],
+    nodes: HashMap<i32, VoxNode>,
+    hidden_layers: HashSet<i32>,
+}
+
+/// Rotation matrix (row-major) and translation in MagicaVoxel world space
+#[derive(Debug, Clone, Copy)]
+struct VoxTransform {
+    rotation: [[i32; 3]; 3],
+    translation: IVec3,
+}
+
+impl VoxTransform {
+    const IDENTITY: Self = Self {
+        rotation: [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+        translation: IVec3::ZERO,
+    };
+
+    /// Decode the packed `_r` rotation byte
+    ///
+    /// Bits 0-1 and 2-3 hold the column of the non-zero entry in rows 0 and 1;
+    /// bits 4-6 flip the sign of rows 0-2.
+    fn from_parts(rotation: u8, translation: IVec3) -> Option<Self> {
+        let first = (rotation & 0b11) as usize;
+        let second = ((rotation >> 2) & 0b11) as usize;
+        if first > 2 || second > 2 || first == second {
+            return None;
+        }
+        let mut matrix = [[0; 3]; 3];
+        for (row, column) in [first, second, 3 - first - second].into_iter().enumerate() {
+            matrix[row][column] = if rotation & (1 << (4 + row)) != 0 { -1 } else { 1 };
+        }
+        Some(Self { rotation: matrix, translation })
+    }
+
+    fn rotate(&self, v: IVec3) -> IVec3 {
+        let v = v.to_array();
+        let row = |r: [i32; 3]| r[0] * v[0] + r[1] * v[1] + r[2] * v[2];
+        IVec3::new(row(self.rotation[0]), row(self.rotation[1]), row(self.rotation[2]))
+    }
+
+    fn apply(&self, v: IVec3) -> IVec3 {
+        self.rotate(v) + self.translation
+    }
+
+    /// `self` applied after `child`
+    fn then(&self, child: &VoxTransform) -> VoxTransform {
+        let columns = [IVec3::X, IVec3::Y, IVec3::Z].map(|axis| self.rotate(child.rotate(axis)));
+        let rotation = [0, 1, 2].map(|row| [columns[0][row], columns[1][row], columns[2][row]]);
+        VoxTransform { rotation, translation: self.apply(child.translation) }
+    }
+}
+
+impl VoxFile {
+    /// One model as a scene, without scene graph transforms
+    pub fn model_scene(&self, index: usize, name: &str) -> VoxelScene {
+        let model = &self.models[index];
+        let voxels: Vec<Voxel> = model
+            .voxels
+            .iter()
+            .map(|&[x, y, z, i]| self.voxel(IVec3::new(x as i32, z as i32, model.size[1] as i32 - 1 - y as i32), i))
+            .collect();
+        let [w, d, h] = model.size;
+        build_scene(name, (w, h, d), Vec3::ZERO, voxels)
+    }
+
+    /// Every visible model placed by the scene graph, merged into one scene
+    pub fn scene(&self, name: &str) -> Result<VoxelScene, VoxelLoaderError> {
+        self.scene_with(name, TierLimitPolicy::Reject)
+    }
+
+    /// [`Self::scene`], keeping the first voxels placed up to the tier limit under [`TierLimitPolicy::Truncate`]
+    pub fn scene_with(&self, name: &str, tier_limit: TierLimitPolicy) -> Result<VoxelScene, VoxelLoaderError> {
+        self.merged_scene(name, (tier_limit == TierLimitPolicy::Truncate).then(crate::tier::max_voxels))
+    }
+
+    /// Merge the placed models, stopping after `truncate_at` placements instead of failing the tier check
+    fn merged_scene(&self, name: &str, truncate_at: Option<usize>) -> Result<VoxelScene, VoxelLoaderError> {
+        let mut placed: Vec<(IVec3, u8)> = Vec::new();
+        let limit = truncate_at.unwrap_or(usize::MAX);
+        if self.nodes.contains_key(&0) {
+            // Groups can list a child many times, so count before placing anything
+            let count = self.placed_count(0, &mut HashMap::new(), 0)?;
+            let tier_limit = crate::tier::max_voxels();
+            if truncate_at.is_none() && count > tier_limit as u64 {
+                return Err(VoxelError::TierLimitReached {
+                    current: usize::try_from(count).unwrap_or(usize::MAX),
+                    limit: tier_limit,
+                    tier: crate::tier::current_tier(),
+                }
+                .into());
+            }
+            if count > limit as u64 {
+                warn!("{}: truncating {} placed voxels to the {} tier limit ({})", name, count, crate::tier::current_tier().name(), limit);
+            }
+            self.place(0, VoxTransform::IDENTITY, &mut placed, limit, 0)?;
+        } else {
+            // Files without a scene graph: every model at the origin
+            for model in &self.models {
+                self.place_model(model, &VoxTransform::IDENTITY, &mut placed, limit);
+            }
+        }
+
+        // Later models overwrite earlier ones at the same position
+        let mut index: HashMap<IVec3, usize> = HashMap::new();
+        let mut merged: Vec<(IVec3, u8)> = Vec::new();
+        for (position, palette_index) in placed {
+            let bevy_position = IVec3::new(position.x, position.z, -position.y);
+            match index.get(&bevy_position) {
+                Some(&i) => merged[i].1 = palette_index,
+                None => {
+                    index.insert(bevy_position, merged.len());
+                    merged.push((bevy_position, palette_index));
+                }
+            }
+        }
+
+        if merged.is_empty() {
+            return Ok(build_scene(name, (0, 0, 0), Vec3::ZERO, Vec::new()));
+        }
+        let min = merged.iter().fold(IVec3::MAX, |min, (p, _)| min.min(*p));
+        let max = merged.iter().fold(IVec3::MIN, |max, (p, _)| max.max(*p));
+        let extent = max - min + IVec3::ONE;
+        if extent.max_element() > u16::MAX as i32 + 1 {
+            return Err(invalid(format!("Scene spans {:?} voxels, more than the voxel grid holds", extent)));
+        }
+
+        let voxels = merged
+            .into_iter()
+            .map(|(p, i)| self.voxel(p - min, i))
+            .collect();
+        let dimensions = (extent.x as u32, extent.y as u32, extent.z as u32);
+        Ok(build_scene(name, dimensions, min.as_vec3(), voxels))
+    }
+
+    /// Build a voxel from a non-negative Y-up position and palette index
+    fn voxel(&self, p: IVec3, index: u8) -> Voxel {
+        Voxel {
+            position: [p.x as u16, p.y as u16, p.z as u16],
+            color: self.palette[index as usize],
+            material_id: index,
+        }
+    }
+
+    /// Voxels placing node `id` would produce, before overlaps merge
+    fn placed_count(&self, id: i32, counts: &mut HashMap<i32, u64>, depth: usize) -> Result<u64, VoxelLoaderError> {
+        if depth > MAX_GRAPH_DEPTH {
+            return Err(invalid("Scene graph too deep"));
+        }
+        if let Some(&count) = counts.get(&id) {
+            return Ok(count);
+        }
+        let count = match self.nodes.get(&id) {
+            Some(VoxNode::Transform { child, layer, hidden, .. }) => {
+                if *hidden || self.hidden_layers.contains(layer) {
+                    0
+                } else {
+                    self.placed_count(*child, counts, depth + 1)?
+                }
+            }
+            Some(VoxNode::Group { children, hidden }) => {
+                let mut total = 0u64;
+                if !*hidden {
+                    for child in children {
+                        total = total.saturating_add(self.placed_count(*child, counts, depth + 1)?);
+                    }
+                }
+                total
+            }
+            Some(VoxNode::Shape { models }) => models
+                .iter()
+                .map(|&m| self.models.get(m as usize).map_or(0, |model| model.voxels.len() as u64))
+                .fold(0u64, u64::saturating_add),
+            None => return Err(invalid(format!("Missing scene graph node {}", id))),
+        };
+        counts.insert(id, count);
+        Ok(count)
+    }
+
+    /// Place node `id` and its children, stopping once `out` holds `limit` voxels
+    fn place(
+        &self,
+        id: i32,
+        parent: VoxTransform,
+        out: &mut Vec<(IVec3, u8)>,
+        limit: usize,
+        depth: usize,
+    ) -> Result<(), VoxelLoaderError> {
+        // Guard against cycles in malformed files
+        if depth > MAX_GRAPH_DEPTH {
+            return Err(invalid("Scene graph too deep"));
+        }
+        if out.len() >= limit {
+            return Ok(());
+        }
+        match self.nodes.get(&id) {
+            Some(VoxNode::Transform { child, layer, rotation, translation, hidden }) => {
+                if *hidden || self.hidden_layers.contains(layer) {
+                    return Ok(());
+                }
+                let local = VoxTransform::from_parts(*rotation, *translation)
+                    .ok_or_else(|| invalid(format!("Invalid rotation {} on node {}", rotation, id)))?;
+                let transform = parent.then(&local);
+                self.place(*child, transform, out, limit, depth + 1)
+            }
+            Some(VoxNode::Group { children, hidden }) => {
+                if !*hidden {
+                    for child in children {
+                        self.place(*child, parent, out, limit, depth + 1)?;
+                    }
+                }
+                Ok(())
+            }
+            Some(VoxNode::Shape { models }) => {
+                for &model in models {
+                    let model = self
+                        .models
+                        .get(model as usize)
+                        .ok_or_else(|| invalid(format!("Shape references missing model {}", model)))?;
+                    self.place_model(model, &parent, out, limit);
+                }
+                Ok(())
+            }
+            None => Err(invalid(format!("Missing scene graph node {}", id))),
+        }
+    }
+
+    fn place_model(&self, model: &VoxModel, transform: &VoxTransform, out: &mut Vec<(IVec3, u8)>, limit: usize) {
+        // Models are centered on their transform
+        let center = IVec3::new(model.size[0] as i32 / 2, model.size[1] as i32 / 2, model.size[2] as i32 / 2);
+        for &[x, y, z, i] in model.voxels.iter().take(limit.saturating_sub(out.len())) {
+            out.push((transform.apply(IVec3::new(x as i32, y as i32, z as i32) - center), i));
+        }
+    }
+}
+
+fn build_scene(name: &str, dimensions: (u32, u32, u32), origin: Vec3, voxels: Vec<Voxel>) -> VoxelScene {
+    VoxelScene::from_voxels(VoxelMetadata { origin, ..VoxelMetadata::new(name, dimensions) }, voxels)
+}
+
+/// Parse a `.vox` file
+pub fn parse_vox(bytes: &[u8]) -> Result<VoxFile, VoxelLoaderError> {
+    let mut reader = VoxReader { bytes };
+    if reader.take(4)? != b"VOX " {
+        return Err(invalid("Invalid magic header (expected VOX)"));
+    }
+    let _version = reader.i32()?;
+
+    let (id, _content, children) = reader.chunk()?;
+    if id != *b"MAIN" {
+        return Err(invalid("Missing MAIN chunk"));
+    }
+
+    let mut file = VoxFile {
+        models: Vec::new(),
+        palette: default_palette(),
+        nodes: HashMap::new(),
+        hidden_layers: HashSet::new(),
+    };
+    let mut children = VoxReader { bytes: children };
+    let mut size = None;
+    while !children.bytes.is_empty() {
+        let (id, content, _) = children.chunk()?;
+        let mut content = VoxReader { bytes: content };
+        match &id {
+            b"SIZE" => {
+                let model_size = [content.u32()?, content.u32()?, content.u32()?];
+                if model_size.iter().any(|&s| s > MAX_MODEL_SIZE as u32) {
+                    return Err(invalid(format!("Model size {:?} exceeds {}", model_size, MAX_MODEL_SIZE)));
+                }
+                size = Some(model_size);
+            }
+            b"XYZI" => {
+                let size = size.take().ok_or_else(|| invalid("XYZI chunk without SIZE"))?;
+                let count = content.u32()? as usize;
+                let data = content.take(count.checked_mul(4).ok_or_else(|| invalid("Too many voxels"))?)?;
+                let voxels = data.chunks_exact(4).map(|v| [v[0], v[1], v[2], v[3]]).collect();
+                file.models.push(VoxModel { size, voxels });
+            }
+            b"RGBA" => {
+                // Entry i is palette index i + 1
+                let colors = content.take(256 * 4)?;
+                for (i, color) in colors.chunks_exact(4).take(255).enumerate() {
+                    file.palette[i + 1] = [color[0], color[1], color[2], color[3]];
+                }
+            }
+            b"nTRN" => {
+                let id = content.i32()?;
+                let attributes = content.dict()?;
+                let child = content.i32()?;
+                let _reserved = content.i32()?;
+                let layer = content.i32()?;
+                let frames = content.i32()?;
+                let mut rotation = 0b0000_0100;
+                let mut translation = IVec3::ZERO;
+                // Only the first animation frame is used
+                for frame in 0..frames.max(0) {
+                    let frame_attributes = content.dict()?;
+                    if frame > 0 {
+                        continue;
+                    }
+                    if let Some(r) = frame_attributes.get("_r") {
+                        rotation = r.parse().map_err(|_| invalid(format!("Invalid rotation {:?}", r)))?;
+                    }
+                    if let Some(t) = frame_attributes.get("_t") {
+                        let parts: Vec<i32> = t.split_whitespace().filter_map(|p| p.parse().ok()).collect();
+                        let [x, y, z] = parts[..] else {
+                            return Err(invalid(format!("Invalid translation {:?}", t)));
+                        };
+                        if [x, y, z].iter().any(|c| c.unsigned_abs() > MAX_TRANSLATION as u32) {
+                            return Err(invalid(format!("Translation {:?} out of range", t)));
+                        }
+                        translation = IVec3::new(x, y, z);
+                    }
+                }
+                let hidden = attributes.get("_hidden").is_some_and(|h| h == "1");
+                file.nodes.insert(id, VoxNode::Transform { child, layer, rotation, translation, hidden });
+            }
+            b"nGRP" => {
+                let id = content.i32()?;
+                let attributes = content.dict()?;
+                let count = content.i32()?;
+                let children = (0..count.max(0)).map(|_| content.i32()).collect::<Result<_, _>>()?;
+                let hidden = attributes.get("_hidden").is_some_and(|h| h == "1");
+                file.nodes.insert(id, VoxNode::Group { children, hidden });
+            }
+            b"nSHP" => {
+                let id = content.i32()?;
+                let _attributes = content.dict()?;
+                let count = content.i32()?;
+                let mut models = Vec::new();
+                for _ in 0..count.max(0) {
+                    models.push(content.i32()?);
+                    let _model_attributes = content.dict()?;
+                }
+                file.nodes.insert(id, VoxNode::Shape { models });
+            }
+            b"LAYR" => {
+                let id = content.i32()?;
+                if content.dict()?.get("_hidden").is_some_and(|h| h == "1") {
+                    file.hidden_layers.insert(id);
+                }
+            }
+            // PACK, MATL, rOBJ, rCAM, NOTE, IMAP and future chunks
+            _ => {}
+        }
+    }
+    Ok(file)
+}
+
+/// MagicaVoxel's built-in palette, used when a file has no `RGBA` chunk
+pub fn default_palette() -> [[u8; 4]; 256] {
+    const LEVELS: [u8; 6] = [0xFF, 0xCC, 0x99, 0x66, 0x33, 0x00];
+    const RAMP: [u8; 10] = [0xEE, 0xDD, 0xBB, 0xAA, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+
+    let mut palette = [[0; 4]; 256];
+    let mut i = 1;
+    // 6x6x6 color cube (blue fastest), without black
+    for r in LEVELS {
+        for g in LEVELS {
+            for b in LEVELS {
+                if i < 216 {
+                    palette[i] = [r, g, b, 0xFF];
+                    i += 1;
+                }
+            }
+        }
+    }
+    // Red, green, blue and gray ramps
+    for channel in 0..4 {
+        for level in RAMP {
+            palette[i] = match channel {
+                0 => [level, 0, 0, 0xFF],
+                1 => [0, level, 0, 0xFF],
+                2 => [0, 0, level, 0xFF],
+                _ => [level, level, level, 0xFF],
+            };
+            i += 1;
+        }
+    }
+    palette
+}
+
+/// Largest model edge MagicaVoxel accepts
+pub const MAX_MODEL_SIZE: i32 = 256;
+
+/// Serialize a scene as a `.vox` file
+pub(crate) fn write_vox(scene: &VoxelScene) -> Result<Vec<u8>, String> {
+    let VoxelData::Community(data) = &scene.voxel_data else {
+        return Err("Cannot export Professional tier data yet".to_string());
+    };
+
+    let mut counts: HashMap<[u8; 4], usize> = HashMap::new();
+    for voxel in data.voxels.iter() {
+        *counts.entry(voxel.color).or_default() += 1;
+    }
+    let (palette, palette_index) = quantize_palette(&counts, 255);
+
+    // MagicaVoxel world positions, grouped into 256³ blocks
+    let offset = scene.metadata.origin.round().as_ivec3();
+    let mut blocks: std::collections::BTreeMap<[i32; 3], Vec<(IVec3, u8)>> = default();
+    for voxel in data.voxels.iter() {
+        let [x, y, z] = voxel.position.map(|c| c as i32);
+        let block = [x, y, z].map(|c| c.div_euclid(MAX_MODEL_SIZE));
+        let world = IVec3::new(x, y, z) + offset;
+        let index = palette_index[&voxel.color];
+        blocks.entry(block).or_default().push((IVec3::new(world.x, -world.z, world.y), index));
+    }
+
+    let mut models = Vec::new();
+    let mut graph = Vec::new();
+    let model_count = blocks.len() as i32;
+    graph.extend(vox_chunk(b"nTRN", &[vox_ints(&[0]), vox_dict(&[]), vox_ints(&[1, -1, 0, 1]), vox_dict(&[])].concat()));
+    let children: Vec<i32> = (0..model_count).map(|i| 2 + i * 2).collect();
+    graph.extend(vox_chunk(b"nGRP", &[vox_ints(&[1]), vox_dict(&[]), vox_ints(&[model_count]), vox_ints(&children)].concat()));
+
+    for (i, voxels) in blocks.values().enumerate() {
+        let min = voxels.iter().fold(IVec3::MAX, |min, (p, _)| min.min(*p));
+        let max = voxels.iter().fold(IVec3::MIN, |max, (p, _)| max.max(*p));
+        let size = max - min + IVec3::ONE;
+
+        let mut xyzi = (voxels.len() as u32).to_le_bytes().to_vec();
+        for (p, index) in voxels {
+            let local = *p - min;
+            xyzi.extend_from_slice(&[local.x as u8, local.y as u8, local.z as u8, *index]);
+        }
+        let size_bytes: Vec<u8> = size.to_array().iter().flat_map(|s| (*s as u32).to_le_bytes()).collect();
+        models.extend(vox_chunk(b"SIZE", &size_bytes));
+        models.extend(vox_chunk(b"XYZI", &xyzi));
+
+        // The importer centers models on their transform
+        let translation = min + size / 2;
+        let id = children[i];
+        let t = format!("{} {} {}", translation.x, translation.y, translation.z);
+        graph.extend(vox_chunk(
+            b"nTRN",
+            &[vox_ints(&[id]), vox_dict(&[]), vox_ints(&[id + 1, -1, 0, 1]), vox_dict(&[("_t", &t)])].concat(),
+        ));
+        graph.extend(vox_chunk(b"nSHP", &[vox_ints(&[id + 1]), vox_dict(&[]), vox_ints(&[1, i as i32]), vox_dict(&[])].concat()));
+    }
+    graph.extend(vox_chunk(b"LAYR", &[vox_ints(&[0]), vox_dict(&[]), vox_ints(&[-1])].concat()));
+
+    // RGBA entry i is palette index i + 1
+    let mut rgba = vec![0u8; 256 * 4];
+    for (i, color) in palette.iter().enumerate() {
+        rgba[i * 4..i * 4 + 4].copy_from_slice(color);
+    }
+
+    let children = [models, graph, vox_chunk(b"RGBA", &rgba)].concat();
+    let mut bytes = b"VOX ".to_vec();
+    bytes.extend_from_slice(&200i32.to_le_bytes());
+    bytes.extend_from_slice(b"MAIN");
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&children);
+    Ok(bytes)
+}
+
+/// Reduce colors to at most `max` palette entries with median cut
+///
+/// Returns the palette and the 1-based palette index of every input color.
+/// Inputs with no more than `max` distinct colors are kept exactly.
+fn quantize_palette(counts: &HashMap<[u8; 4], usize>, max: usize) -> (Vec<[u8; 4]>, HashMap<[u8; 4], u8>) {
+    let mut colors: Vec<([u8; 4], usize)> = counts.iter().map(|(c, n)| (*c, *n)).collect();
+    colors.sort();
+    let mut boxes = vec![colors];
+
+    while boxes.len() < max {
+        // Split the box with the widest channel range
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = (0..4)
+                    .map(|ch| {
+                        let (lo, hi) = b.iter().fold((255, 0), |(lo, hi), (c, _)| (c[ch].min(lo), c[ch].max(hi)));
+                        (ch, hi - lo)
+                    })
+                    .max_by_key(|(_, range)| *range)
+                    .unwrap();
+                (i, channel, range)
+            })
+            .max_by_key(|(_, _, range)| *range);
+        let Some((i, channel, _)) = widest else { break };
+
+        let mut split = boxes.swap_remove(i);
+        split.sort_by_key(|(c, _)| c[channel]);
+        let total: usize = split.iter().map(|(_, n)| n).sum();
+        let mut seen = 0;
+        let median = split
+            .iter()
+            .position(|(_, n)| {
+                seen += n;
+                seen * 2 >= total
+            })
+            .unwrap_or(0)
+            .clamp(0, split.len() - 2)
+            + 1;
+        let upper = split.split_off(median);
+        boxes.push(split);
+        boxes.push(upper);
+    }
+
+    let mut palette = Vec::with_capacity(boxes.len());
+    let mut index = HashMap::new();
+    for (i, group) in boxes.iter().enumerate() {
+        let total: usize = group.iter().map(|(_, n)| n).sum();
+        let mut sum = [0usize; 4];
+        for (color, n) in group {
+            for ch in 0..4 {
+                sum[ch] += color[ch] as usize * n;
+            }
+            index.insert(*color, i as u8 + 1);
+        }
+        palette.push(sum.map(|s| ((s + total / 2) / total.max(1)) as u8));
+    }
+    (palette, index)
+}
+
+fn vox_chunk(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+    let mut bytes = id.to_vec();
+    bytes.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(content);
+    bytes
+}
+
+fn vox_ints(values: &[i32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn vox_dict(pairs: &[(&str, &str)]) -> Vec<u8> {
+    let mut bytes = (pairs.len() as u32).to_le_bytes().to_vec();
+    for s in pairs.iter().flat_map(|(k, v)| [k, v]) {
+        bytes.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(s.as_bytes());
+    }
+    bytes
+}
+
+fn invalid(message: impl Into<String>) -> VoxelLoaderError {
+    VoxelLoaderError::InvalidVox(message.into())
+}
+
+/// Chunk id, content bytes and children bytes
+type RawChunk<'a> = ([u8; 4], &'a [u8], &'a [u8]);
+
+/// Bounds-checked reader for .vox chunks
+struct VoxReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> VoxReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], VoxelLoaderError> {
+        if len > self.bytes.len() {
+            return Err(invalid(format!("Unexpected end of data: need {} bytes, have {}", len, self.bytes.len())));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, VoxelLoaderError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, VoxelLoaderError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, VoxelLoaderError> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn dict(&mut self) -> Result<HashMap<String, String>, VoxelLoaderError> {
+        let count = self.u32()?;
+        let mut dict = HashMap::new();
+        for _ in 0..count {
+            let key = self.string()?;
+            dict.insert(key, self.string()?);
+        }
+        Ok(dict)
+    }
+
+    /// Read one chunk: id, content and children bytes
+    fn chunk(&mut self) -> Result<RawChunk<'a>, VoxelLoaderError> {
+        let id: [u8; 4] = self.take(4)?.try_into().unwrap();
+        let content_len = self.u32()? as usize;
+        let children_len = self.u32()? as usize;
+        Ok((id, self.take(content_len)?, self.take(children_len)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], content: &[u8], children: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(content);
+        bytes.extend_from_slice(children);
+        bytes
+    }
+
+    fn model(size: [u32; 3], voxels: &[[u8; 4]]) -> Vec<u8> {
+        let size: Vec<u8> = size.iter().flat_map(|s| s.to_le_bytes()).collect();
+        let mut xyzi = (voxels.len() as u32).to_le_bytes().to_vec();
+        xyzi.extend(voxels.iter().flatten());
+        [chunk(b"SIZE", &size, &[]), chunk(b"XYZI", &xyzi, &[])].concat()
+    }
+
+    use super::{vox_dict as dict, vox_ints as ints};
+
+    fn transform(id: i32, child: i32, translation: &str) -> Vec<u8> {
+        let content = [ints(&[id]), dict(&[]), ints(&[child, -1, 0, 1]), dict(&[("_t", translation)])].concat();
+        chunk(b"nTRN", &content, &[])
+    }
+
+    fn vox_file(children: Vec<u8>) -> Vec<u8> {
+        [b"VOX ".to_vec(), ints(&[150]), chunk(b"MAIN", &[], &children)].concat()
+    }
+
+    #[test]
+    fn test_single_model_with_palette() {
+        let mut rgba = vec![0u8; 1024];
+        rgba[..4].copy_from_slice(&[10, 20, 30, 255]); // palette index 1
+        rgba[199 * 4..200 * 4].copy_from_slice(&[1, 2, 3, 128]); // palette index 200
+        let bytes = vox_file([model([2, 3, 4], &[[0, 0, 0, 1], [1, 2, 3, 200]]), chunk(b"RGBA", &rgba, &[])].concat());
+
+        let vox = parse_vox(&bytes).unwrap();
+        let scene = vox.model_scene(0, "single");
+        assert_eq!(scene.metadata.dimensions, (2, 4, 3));
+
+        let voxels = scene.voxels();
+        assert_eq!(voxels[0].color, [10, 20, 30, 255]);
+        assert_eq!(voxels[0].material_id, 1);
+        // Z-up (1, 2, 3) becomes Y-up (1, 3, 0) after flipping y into -z
+        assert_eq!(voxels[1].position, [1, 3, 0]);
+        assert_eq!(voxels[1].color, [1, 2, 3, 128]);
+        assert_eq!(voxels[1].material_id, 200);
+    }
+
+    #[test]
+    fn test_scene_graph_places_models() {
+        let group = chunk(b"nGRP", &[ints(&[1]), dict(&[]), ints(&[2, 2, 4])].concat(), &[]);
+        let shape = |id: i32, model: i32| chunk(b"nSHP", &[ints(&[id]), dict(&[]), ints(&[1, model]), dict(&[])].concat(), &[]);
+        let bytes = vox_file(
+            [
+                model([1, 1, 1], &[[0, 0, 0, 5]]),
+                model([1, 1, 1], &[[0, 0, 0, 6]]),
+                transform(0, 1, "0 0 0"),
+                group,
+                transform(2, 3, "0 0 0"),
+                shape(3, 0),
+                transform(4, 5, "10 0 2"),
+                shape(5, 1),
+            ]
+            .concat(),
+        );
+
+        let scene = parse_vox(&bytes).unwrap().scene("graph").unwrap();
+        assert_eq!(scene.voxel_count(), 2);
+        assert_eq!(scene.metadata.dimensions, (11, 3, 1));
+        let second = scene.voxels().iter().find(|v| v.material_id == 6).unwrap();
+        assert_eq!(second.position, [10, 2, 0]);
+    }
+
+    #[test]
+    fn test_rejects_hostile_scene_graphs() {
+        let shape = chunk(b"nSHP", &[ints(&[3]), dict(&[]), ints(&[1, 0]), dict(&[])].concat(), &[]);
+
+        // Translations near i32::MAX would overflow while placing
+        let far = vox_file([model([1, 1, 1], &[[0, 0, 0, 1]]), transform(0, 3, "2147483647 0 0"), shape.clone()].concat());
+        assert!(matches!(parse_vox(&far), Err(VoxelLoaderError::InvalidVox(_))));
+
+        // Each group lists the next transform 4096 times: 4096³ placements of one voxel
+        let group = |id: i32, child: i32| {
+            let mut content = [ints(&[id]), dict(&[]), ints(&[4096])].concat();
+            content.extend(ints(&[child]).repeat(4096));
+            chunk(b"nGRP", &content, &[])
+        };
+        let bytes = vox_file(
+            [
+                model([1, 1, 1], &[[0, 0, 0, 1]]),
+                transform(0, 1, "0 0 0"),
+                group(1, 4),
+                transform(4, 5, "0 0 0"),
+                group(5, 6),
+                transform(6, 7, "0 0 0"),
+                group(7, 2),
+                transform(2, 3, "0 0 0"),
+                shape,
+            ]
+            .concat(),
+        );
+        let vox = parse_vox(&bytes).unwrap();
+        if crate::tier::max_voxels() < 1 << 36 {
+            assert!(matches!(vox.scene("bomb"), Err(VoxelLoaderError::TierLimit(_))));
+        }
+
+        // Truncating stops placing at the limit; the copies all land on one voxel
+        let truncated = vox.merged_scene("bomb", Some(1000)).unwrap();
+        assert_eq!(truncated.voxel_count(), 1);
+    }
+
+    #[test]
+    fn test_truncates_placed_voxels_to_tier_limit() {
+        let row: Vec<[u8; 4]> = (0..200).map(|x| [x as u8, 0, 0, 1]).collect();
+        let shape = chunk(b"nSHP", &[ints(&[1]), dict(&[]), ints(&[1, 0]), dict(&[])].concat(), &[]);
+        let bytes = vox_file([model([200, 1, 1], &row), transform(0, 1, "0 0 0"), shape].concat());
+        let vox = parse_vox(&bytes).unwrap();
+
+        // Stand-in for the tier limit, which is too large to place in a test
+        let scene = vox.merged_scene("row", Some(50)).unwrap();
+        assert_eq!(scene.voxel_count(), 50);
+        assert_eq!(scene.metadata.dimensions, (50, 1, 1));
+        assert_eq!(vox.scene_with("row", TierLimitPolicy::Truncate).unwrap().voxel_count(), 200);
+    }
+
+    fn sorted_voxels(scene: &VoxelScene) -> Vec<([u16; 3], [u8; 4])> {
+        let mut voxels: Vec<_> = scene.voxels().iter().map(|v| (v.position, v.color)).collect();
+        voxels.sort();
+        voxels
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let scene = VoxelScene::test_cube(4);
+        let imported = parse_vox(&scene.to_vox().unwrap()).unwrap().scene("roundtrip").unwrap();
+
+        assert_eq!(imported.metadata.dimensions, scene.metadata.dimensions);
+        assert_eq!(sorted_voxels(&imported), sorted_voxels(&scene));
+    }
+
+    #[test]
+    fn test_export_splits_large_scenes() {
+        let mut scene = VoxelScene::test_cube(1);
+        scene.add_voxel(Voxel { position: [300, 2, 600], color: [9, 9, 9, 255], material_id: 0 }).unwrap();
+
+        let vox = parse_vox(&scene.to_vox().unwrap()).unwrap();
+        assert_eq!(vox.models.len(), 2);
+        assert_eq!(sorted_voxels(&vox.scene("split").unwrap()), sorted_voxels(&scene));
+    }
+
+    #[test]
+    fn test_export_quantizes_palette() {
+        // 512 distinct colors
+        let mut scene = VoxelScene::test_cube(8);
+        scene.fill_region([0, 0, 0], [7, 7, 7], [0; 4], 0).unwrap();
+        if let VoxelData::Community(data) = &mut scene.voxel_data {
+            for (i, voxel) in std::sync::Arc::make_mut(&mut data.voxels).iter_mut().enumerate() {
+                voxel.color = [(i % 8 * 32) as u8, (i / 8 % 8 * 32) as u8, (i / 64 * 32) as u8, 255];
+            }
+        }
+
+        let imported = parse_vox(&scene.to_vox().unwrap()).unwrap().scene("quantized").unwrap();
+        let distinct: HashSet<[u8; 4]> = imported.voxels().iter().map(|v| v.color).collect();
+        assert!(distinct.len() <= 255);
+        for ((position, original), (imported_position, color)) in sorted_voxels(&scene).into_iter().zip(sorted_voxels(&imported)) {
+            assert_eq!(position, imported_position);
+            assert!((0..3).all(|ch| original[ch].abs_diff(color[ch]) <= 32), "{:?} -> {:?}", original, color);
+        }
+    }
+
+    #[test]
+    fn test_default_palette_layout() {
+        let palette = default_palette();
+        assert_eq!(palette[0], [0, 0, 0, 0]);
+        assert_eq!(palette[1], [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(palette[2], [0xFF, 0xFF, 0xCC, 0xFF]);
+        assert_eq!(palette[216], [0xEE, 0, 0, 0xFF]);
+        assert_eq!(palette[255], [0x11, 0x11, 0x11, 0xFF]);
+    }
+}